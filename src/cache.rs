@@ -0,0 +1,556 @@
+use crate::watch::WatchedFile;
+use anyhow::Context;
+use emfcamp_schedule_api::{schedule::Schedule, Client as ScheduleClient};
+use metrics::{counter, gauge, histogram};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+/// Delay before the first retry of a failed schedule API request, doubling with each retry.
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Longest delay between retries, however many there have been.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Delay before retrying a request that has already been retried `attempt` times.
+fn retry_delay(attempt: u32) -> Duration {
+    RETRY_DELAY
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(MAX_RETRY_DELAY)
+}
+
+/// Ways that fetching the schedule can fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FailureKind {
+    /// The API did not respond in time.
+    Timeout,
+
+    /// The API (or file) could not be reached.
+    Connection,
+
+    /// The API responded with an HTTP error status.
+    Status(u16),
+
+    /// The schedule could not be parsed.
+    Parse,
+
+    Other,
+}
+
+impl FailureKind {
+    /// Classifies an error by the errors that caused it.
+    pub(crate) fn classify(error: &anyhow::Error) -> Self {
+        for cause in error.chain() {
+            if cause.is::<tokio::time::error::Elapsed>() {
+                return Self::Timeout;
+            }
+            if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+                if e.is_timeout() {
+                    return Self::Timeout;
+                }
+                if e.is_connect() {
+                    return Self::Connection;
+                }
+                if let Some(status) = e.status() {
+                    return Self::Status(status.as_u16());
+                }
+                if e.is_decode() {
+                    return Self::Parse;
+                }
+            }
+            if let Some(e) = cause.downcast_ref::<std::io::Error>() {
+                return match e.kind() {
+                    std::io::ErrorKind::TimedOut => Self::Timeout,
+                    _ => Self::Connection,
+                };
+            }
+            if cause.is::<serde_json::Error>() {
+                return Self::Parse;
+            }
+        }
+
+        Self::Other
+    }
+
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            Self::Timeout => "timeout",
+            Self::Connection => "connection",
+            Self::Status(_) => "status",
+            Self::Parse => "parse",
+            Self::Other => "other",
+        }
+    }
+
+    /// Whether trying again might succeed, i.e. the API could not be reached, failed or asked for
+    /// requests to slow down, rather than rejecting the request.
+    fn is_transient(self) -> bool {
+        match self {
+            Self::Timeout | Self::Connection => true,
+            Self::Status(status) => status >= 500 || status == 429,
+            Self::Parse | Self::Other => false,
+        }
+    }
+}
+
+/// Where the schedule comes from.
+pub(crate) enum ScheduleSource {
+    Api {
+        client: ScheduleClient,
+
+        /// Time to wait for each request before giving up on it.
+        timeout: Duration,
+
+        /// Number of times a request that failed in a way that might be temporary is retried.
+        retries: u32,
+    },
+    File(WatchedFile),
+}
+
+impl ScheduleSource {
+    pub(crate) fn api(client: ScheduleClient, timeout: Duration, retries: u32) -> Self {
+        Self::Api {
+            client,
+            timeout,
+            retries,
+        }
+    }
+
+    pub(crate) fn file(path: PathBuf) -> Self {
+        Self::File(WatchedFile::new(path))
+    }
+
+    /// Gets the schedule from the source, or `None` if it is known not to have changed since it
+    /// was last fetched.
+    ///
+    /// Requests to the API that fail in a way that might be temporary are retried if `retry` is
+    /// set.
+    pub(crate) async fn fetch(&self, retry: bool) -> anyhow::Result<Option<Schedule>> {
+        match self {
+            Self::Api {
+                client,
+                timeout,
+                retries,
+            } => {
+                let mut attempt = 0;
+
+                loop {
+                    let started = Instant::now();
+                    let result = tokio::time::timeout(*timeout, client.get_schedule()).await;
+                    let latency = started.elapsed().as_secs_f64();
+
+                    let error = match result {
+                        Ok(Ok(schedule)) => {
+                            histogram!(crate::METRIC_API_LATENCY_NAME, "outcome" => "success")
+                                .record(latency);
+                            return Ok(Some(schedule));
+                        }
+                        Ok(Err(e)) => anyhow::Error::from(e),
+                        Err(e) => anyhow::Error::from(e).context("schedule API request timed out"),
+                    };
+
+                    let kind = FailureKind::classify(&error);
+                    histogram!(crate::METRIC_API_LATENCY_NAME, "outcome" => kind.label())
+                        .record(latency);
+                    if !retry || attempt >= *retries || !kind.is_transient() {
+                        return Err(error);
+                    }
+
+                    let delay = retry_delay(attempt);
+                    attempt += 1;
+                    warn!(
+                        "Schedule API request failed ({}), retrying in {delay:?}: {error}",
+                        kind.label()
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+            }
+            Self::File(file) => {
+                let schedule = file
+                    .load_if_changed(|contents| Ok(serde_json::from_slice(&contents)?))
+                    .await?;
+
+                if schedule.is_some() {
+                    info!("Loaded schedule from {}", file.path().display());
+                }
+
+                Ok(schedule)
+            }
+        }
+    }
+
+    /// Writes the current schedule to a file that can later be used as a schedule source.
+    pub(crate) async fn snapshot(&self, output: &Path) -> anyhow::Result<()> {
+        let schedule = self
+            .fetch(true)
+            .await?
+            .context("schedule source has not changed")?;
+
+        // Write via a temporary file so that a server watching the output never sees a partial file
+        let temporary = output.with_extension("tmp");
+        tokio::fs::write(&temporary, serde_json::to_vec_pretty(&schedule)?).await?;
+        tokio::fs::rename(&temporary, output).await?;
+
+        info!(
+            "Wrote {} events to {}",
+            schedule.events.len(),
+            output.display()
+        );
+
+        Ok(())
+    }
+}
+
+/// A copy of the schedule fetched at some point in the past.
+pub(crate) struct CachedSchedule {
+    pub schedule: Schedule,
+
+    /// Set if the schedule has not been successfully refreshed for longer than the staleness
+    /// threshold.
+    pub stale: bool,
+}
+
+struct Entry {
+    schedule: Schedule,
+    fetched: Instant,
+}
+
+/// Keeps the last good copy of the schedule, refreshed in the background, so that handlers do not
+/// need to wait for (or be affected by failures of) the schedule API.
+pub(crate) struct ScheduleCache {
+    source: ScheduleSource,
+    stale_after: Option<Duration>,
+    entry: RwLock<Option<Entry>>,
+    consecutive_failures: RwLock<u64>,
+}
+
+impl ScheduleCache {
+    pub(crate) fn new(source: ScheduleSource, stale_after: Option<Duration>) -> Self {
+        Self {
+            source,
+            stale_after,
+            entry: Default::default(),
+            consecutive_failures: Default::default(),
+        }
+    }
+
+    /// Refreshes the schedule on a fixed interval for as long as the application runs.
+    pub(crate) fn spawn_refresh(self: Arc<Self>, interval: Duration) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+
+            loop {
+                interval.tick().await;
+
+                if let Err(e) = self.refresh(true).await {
+                    warn!("Failed to refresh schedule: {e}");
+                }
+                self.update_age_metric().await;
+            }
+        });
+    }
+
+    /// Fetches the schedule, replacing the cached copy if successful.
+    async fn refresh(&self, retry: bool) -> anyhow::Result<Schedule> {
+        match self.source.fetch(retry).await {
+            Ok(schedule) => {
+                let mut entry = self.entry.write().await;
+
+                let schedule = match (schedule, entry.take()) {
+                    (Some(schedule), _) => {
+                        info!("Refreshed schedule ({} events)", schedule.events.len());
+                        schedule
+                    }
+                    // The source has not changed, so the cached copy is still current
+                    (None, Some(previous)) => previous.schedule,
+                    (None, None) => anyhow::bail!("schedule source has not changed"),
+                };
+
+                *entry = Some(Entry {
+                    schedule: schedule.clone(),
+                    fetched: Instant::now(),
+                });
+
+                *self.consecutive_failures.write().await = 0;
+                gauge!(crate::METRIC_SCHEDULE_REFRESH_FAILURES_NAME).set(0.0);
+
+                Ok(schedule)
+            }
+            Err(e) => {
+                let class = FailureKind::classify(&e).label();
+                counter!(crate::METRIC_API_ERRORS_NAME, "class" => class).increment(1);
+
+                let mut failures = self.consecutive_failures.write().await;
+                *failures += 1;
+                gauge!(crate::METRIC_SCHEDULE_REFRESH_FAILURES_NAME).set(*failures as f64);
+
+                Err(e)
+            }
+        }
+    }
+
+    /// Gets the cached schedule, only fetching it if there has never been a successful refresh.
+    ///
+    /// A caller is waiting on the result, so a failed fetch is not retried here; the background
+    /// refresh retries it instead.
+    pub(crate) async fn get(&self) -> anyhow::Result<CachedSchedule> {
+        if let Some(entry) = self.entry.read().await.as_ref() {
+            let age = entry.fetched.elapsed();
+            gauge!(crate::METRIC_SCHEDULE_AGE_NAME).set(age.as_secs_f64());
+
+            return Ok(CachedSchedule {
+                schedule: entry.schedule.clone(),
+                stale: self.stale_after.is_some_and(|limit| age > limit),
+            });
+        }
+
+        let schedule = self.refresh(false).await?;
+        gauge!(crate::METRIC_SCHEDULE_AGE_NAME).set(0.0);
+
+        Ok(CachedSchedule {
+            schedule,
+            stale: false,
+        })
+    }
+
+    async fn update_age_metric(&self) {
+        if let Some(entry) = self.entry.read().await.as_ref() {
+            gauge!(crate::METRIC_SCHEDULE_AGE_NAME).set(entry.fetched.elapsed().as_secs_f64());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures, watch::tests::set_modified};
+    use axum::{http::StatusCode, routing::get, Router};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Serves a single response on a local port, returning the URL to request and the number of
+    /// requests made so far.
+    async fn serve_counted(status: StatusCode, body: Vec<u8>) -> (String, Arc<AtomicUsize>) {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let counter = requests.clone();
+        let app = Router::new().route(
+            "/",
+            get(move || async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                (status, body)
+            }),
+        );
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        (format!("http://{address}/"), requests)
+    }
+
+    /// Serves a single status code on a local port, returning the URL to request.
+    async fn serve(status: StatusCode) -> String {
+        serve_counted(status, Vec::new()).await.0
+    }
+
+    async fn request_error(url: &str) -> anyhow::Error {
+        let error = reqwest::get(url)
+            .await
+            .and_then(|response| response.error_for_status())
+            .unwrap_err();
+        anyhow::Error::from(error).context("schedule API request failed")
+    }
+
+    #[tokio::test]
+    async fn status() {
+        for (status, transient) in [
+            (StatusCode::INTERNAL_SERVER_ERROR, true),
+            (StatusCode::BAD_GATEWAY, true),
+            (StatusCode::SERVICE_UNAVAILABLE, true),
+            (StatusCode::TOO_MANY_REQUESTS, true),
+            (StatusCode::BAD_REQUEST, false),
+            (StatusCode::NOT_FOUND, false),
+        ] {
+            let error = request_error(&serve(status).await).await;
+            let kind = FailureKind::classify(&error);
+
+            assert_eq!(kind, FailureKind::Status(status.as_u16()));
+            assert_eq!(kind.is_transient(), transient, "{status}");
+        }
+    }
+
+    #[tokio::test]
+    async fn connection() {
+        // Bind and drop a listener to find a port that nothing is listening on
+        let address = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let error = request_error(&format!("http://{address}/")).await;
+        let kind = FailureKind::classify(&error);
+
+        assert_eq!(kind, FailureKind::Connection);
+        assert!(kind.is_transient());
+    }
+
+    #[tokio::test]
+    async fn timeout() {
+        let error = tokio::time::timeout(Duration::ZERO, std::future::pending::<()>())
+            .await
+            .unwrap_err();
+        let kind = FailureKind::classify(&anyhow::Error::from(error));
+
+        assert_eq!(kind, FailureKind::Timeout);
+        assert!(kind.is_transient());
+    }
+
+    #[test]
+    fn parse() {
+        let error = serde_json::from_str::<Schedule>("{").unwrap_err();
+        let kind = FailureKind::classify(&anyhow::Error::from(error).context("failed to parse"));
+
+        assert_eq!(kind, FailureKind::Parse);
+        assert!(!kind.is_transient());
+    }
+
+    #[test]
+    fn other() {
+        let kind = FailureKind::classify(&anyhow::anyhow!("no schedule"));
+
+        assert_eq!(kind, FailureKind::Other);
+        assert!(!kind.is_transient());
+    }
+
+    #[test]
+    fn retry_delay_is_bounded() {
+        assert_eq!(retry_delay(0), Duration::from_millis(500));
+        assert_eq!(retry_delay(1), Duration::from_secs(1));
+        assert_eq!(retry_delay(2), Duration::from_secs(2));
+        assert_eq!(retry_delay(10), MAX_RETRY_DELAY);
+        assert_eq!(retry_delay(u32::MAX), MAX_RETRY_DELAY);
+    }
+
+    #[tokio::test]
+    async fn api_failures_are_classified_and_retried() {
+        let schedule = serde_json::to_vec(&fixtures::schedule()).unwrap();
+
+        for (status, body, kind, requests) in [
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Vec::new(),
+                Some(FailureKind::Status(500)),
+                3,
+            ),
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                Vec::new(),
+                Some(FailureKind::Status(429)),
+                3,
+            ),
+            (
+                StatusCode::NOT_FOUND,
+                Vec::new(),
+                Some(FailureKind::Status(404)),
+                1,
+            ),
+            (
+                StatusCode::OK,
+                b"{\"events\": [".to_vec(),
+                Some(FailureKind::Parse),
+                1,
+            ),
+            (StatusCode::OK, schedule, None, 1),
+        ] {
+            let (url, made) = serve_counted(status, body).await;
+            let source = ScheduleSource::api(
+                ScheduleClient::new(url.parse().unwrap()),
+                Duration::from_secs(5),
+                2,
+            );
+
+            let result = source.fetch(true).await;
+            assert_eq!(
+                result.as_ref().err().map(FailureKind::classify),
+                kind,
+                "{status}"
+            );
+            assert_eq!(made.load(Ordering::SeqCst), requests, "{status}");
+            if kind.is_none() {
+                assert_eq!(result.unwrap().unwrap().events.len(), 16);
+            }
+
+            // Nothing is retried while a caller is waiting
+            made.store(0, Ordering::SeqCst);
+            let _ = source.fetch(false).await;
+            assert_eq!(made.load(Ordering::SeqCst), 1, "{status}");
+        }
+    }
+
+    #[tokio::test]
+    async fn file_is_only_reloaded_when_modified() {
+        let path = fixtures::schedule_file(&fixtures::schedule());
+        set_modified(&path, 1000);
+        let source = ScheduleSource::file(path.clone());
+
+        let schedule = source.fetch(false).await.unwrap().unwrap();
+        assert_eq!(schedule.events.len(), 16);
+        assert!(source.fetch(false).await.unwrap().is_none());
+
+        // A change that keeps the modification time is not noticed
+        let one_event = Schedule {
+            events: fixtures::schedule().events.split_off(15),
+        };
+        std::fs::write(&path, serde_json::to_vec(&one_event).unwrap()).unwrap();
+        set_modified(&path, 1000);
+        assert!(source.fetch(false).await.unwrap().is_none());
+
+        set_modified(&path, 2000);
+        let schedule = source.fetch(false).await.unwrap().unwrap();
+        assert_eq!(schedule.events.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn get_fetches_when_empty() {
+        let path = fixtures::temp_path("schedule.json");
+        let cache = ScheduleCache::new(ScheduleSource::file(path.clone()), None);
+
+        let Err(error) = cache.get().await else {
+            panic!("there should be no schedule to get");
+        };
+        assert_eq!(FailureKind::classify(&error), FailureKind::Connection);
+
+        std::fs::write(&path, serde_json::to_vec(&fixtures::schedule()).unwrap()).unwrap();
+        let cached = cache.get().await.unwrap();
+        assert_eq!(cached.schedule.events.len(), 16);
+        assert!(!cached.stale);
+    }
+
+    #[tokio::test]
+    async fn stale_schedule_is_kept_after_failed_refresh() {
+        let path = fixtures::schedule_file(&fixtures::schedule());
+        set_modified(&path, 1000);
+        let cache = ScheduleCache::new(
+            ScheduleSource::file(path.clone()),
+            Some(Duration::from_millis(50)),
+        );
+        assert!(!cache.get().await.unwrap().stale);
+
+        std::fs::write(&path, "{").unwrap();
+        set_modified(&path, 2000);
+        let error = cache.refresh(true).await.unwrap_err();
+        assert_eq!(FailureKind::classify(&error), FailureKind::Parse);
+
+        let cached = cache.get().await.unwrap();
+        assert_eq!(cached.schedule.events.len(), 16);
+        assert!(!cached.stale);
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let cached = cache.get().await.unwrap();
+        assert_eq!(cached.schedule.events.len(), 16);
+        assert!(cached.stale);
+    }
+}
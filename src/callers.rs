@@ -0,0 +1,168 @@
+use hmac::{Hmac, Mac};
+use metrics::counter;
+use rand::Rng;
+use sha2::Sha256;
+use std::{collections::HashMap, str::FromStr, sync::Mutex};
+use tracing::info;
+
+/// Class of callers whose number is withheld.
+const ANONYMOUS: &str = "anonymous";
+
+/// Class of callers that match no rule.
+const OTHER: &str = "other";
+
+/// Puts callers whose number starts with a prefix into a class, parsed from `<class>=<prefix>`.
+#[derive(Debug, Clone)]
+pub(crate) struct CallerClassRule {
+    class: String,
+    prefix: String,
+}
+
+impl FromStr for CallerClassRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (class, prefix) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected <class>=<prefix>, got \"{s}\""))?;
+
+        if class.is_empty() {
+            return Err(format!("class is empty in \"{s}\""));
+        }
+        // An empty prefix would put every caller into the class
+        if prefix.is_empty() {
+            return Err(format!("prefix is empty in \"{s}\""));
+        }
+
+        Ok(Self {
+            class: class.to_string(),
+            prefix: prefix.to_string(),
+        })
+    }
+}
+
+/// Identifies callers only as far as is useful for metrics: by a small set of classes, and by a
+/// keyed hash of their number for counting repeat callers.
+pub(crate) struct Callers {
+    /// Longest prefix first, so that the most specific rule wins.
+    rules: Vec<CallerClassRule>,
+
+    key: Vec<u8>,
+
+    /// Number of calls from each caller, by hash.
+    calls: Mutex<HashMap<String, u64>>,
+}
+
+impl Callers {
+    /// Creates classification rules and hashing, with a random key if none is given, in which case
+    /// hashes cannot be linked across restarts.
+    pub(crate) fn new(mut rules: Vec<CallerClassRule>, key: Option<String>) -> Self {
+        rules.sort_by_key(|rule| std::cmp::Reverse(rule.prefix.len()));
+
+        let key = match key {
+            Some(key) => key.into_bytes(),
+            None => rand::rng().random::<[u8; 32]>().to_vec(),
+        };
+
+        Self {
+            rules,
+            key,
+            calls: Default::default(),
+        }
+    }
+
+    pub(crate) fn classify(&self, from: &str) -> &str {
+        if !from.chars().any(|c| c.is_ascii_digit()) {
+            return ANONYMOUS;
+        }
+
+        self.rules
+            .iter()
+            .find(|rule| from.starts_with(&rule.prefix))
+            .map_or(OTHER, |rule| &rule.class)
+    }
+
+    fn hash(&self, from: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.key)
+            .expect("HMAC should accept a key of any length");
+        mac.update(from.as_bytes());
+        hex::encode(&mac.finalize().into_bytes()[..8])
+    }
+
+    /// Records a finished call, counting whether the caller has called before.
+    pub(crate) fn record(&self, from: &str) {
+        let class = self.classify(from).to_string();
+
+        // Withheld numbers cannot be told apart
+        if class == ANONYMOUS {
+            return;
+        }
+
+        let caller = self.hash(from);
+        let calls = {
+            let mut calls = self
+                .calls
+                .lock()
+                .expect("caller lock should not be poisoned");
+            let count = calls.entry(caller.clone()).or_default();
+            *count += 1;
+            *count
+        };
+
+        info!("Call {calls} from caller {caller} ({class})");
+
+        if calls == 1 {
+            counter!(crate::METRIC_UNIQUE_CALLERS_NAME, "class" => class).increment(1);
+        } else {
+            counter!(crate::METRIC_REPEAT_CALLS_NAME, "class" => class).increment(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn callers() -> Callers {
+        let rules = ["dect=4", "outside=0", "uk=044"]
+            .into_iter()
+            .map(|rule| rule.parse().unwrap())
+            .collect();
+        Callers::new(rules, Some("key".to_string()))
+    }
+
+    #[test]
+    fn parse_rule() {
+        let rule: CallerClassRule = "dect=4".parse().unwrap();
+        assert_eq!(rule.class, "dect");
+        assert_eq!(rule.prefix, "4");
+
+        for rule in ["dect", "=4", "dect=", "="] {
+            assert!(rule.parse::<CallerClassRule>().is_err(), "{rule}");
+        }
+    }
+
+    #[test]
+    fn classify() {
+        let callers = callers();
+
+        assert_eq!(callers.classify("4123"), "dect");
+        assert_eq!(callers.classify("0123456789"), "outside");
+        assert_eq!(callers.classify("04412345678"), "uk");
+        assert_eq!(callers.classify("9123"), OTHER);
+        assert_eq!(callers.classify("anonymous"), ANONYMOUS);
+        assert_eq!(callers.classify(""), ANONYMOUS);
+    }
+
+    #[test]
+    fn hash() {
+        let callers = callers();
+
+        assert_eq!(callers.hash("4123"), callers.hash("4123"));
+        assert_ne!(callers.hash("4123"), callers.hash("4124"));
+        assert_eq!(callers.hash("4123").len(), 16);
+
+        let other = Callers::new(Vec::new(), Some("other key".to_string()));
+        assert_ne!(callers.hash("4123"), other.hash("4123"));
+    }
+}
@@ -0,0 +1,381 @@
+use crate::{phrasing::TimeStyle, speech::Keywords, ssml::Speech};
+use anyhow::Context;
+use rand::seq::IndexedRandom;
+use serde::Deserialize;
+use std::{collections::HashMap, path::Path, sync::Arc};
+use tracing::{error, info, warn};
+
+/// Catalogs of every language that can be offered.
+const LANGUAGES: &[&str] = &[
+    include_str!("../locales/en-GB.toml"),
+    include_str!("../locales/de-DE.toml"),
+];
+
+/// Prompts of every builtin prompt pack, by pack and language.
+const PROMPT_PACKS: &[(&str, &str, &str)] = &[
+    (
+        "silly",
+        "en-GB",
+        include_str!("../prompts/silly/en-GB.toml"),
+    ),
+    (
+        "silly",
+        "de-DE",
+        include_str!("../prompts/silly/de-DE.toml"),
+    ),
+    (
+        "serious",
+        "en-GB",
+        include_str!("../prompts/serious/en-GB.toml"),
+    ),
+    (
+        "serious",
+        "de-DE",
+        include_str!("../prompts/serious/de-DE.toml"),
+    ),
+];
+
+/// A message, or variants of it of which one is chosen at random each time it is said.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum Message {
+    One(String),
+    Variants(Vec<String>),
+}
+
+/// Messages that make up the personality of the line, in one language.
+#[derive(Debug, Deserialize)]
+struct PromptPack {
+    messages: HashMap<String, Message>,
+}
+
+/// Where prompt packs are loaded from.
+pub(crate) struct Prompts<'a> {
+    /// Name of the prompt pack.
+    pub pack: &'a str,
+
+    /// Directory containing prompt packs, as `<pack>/<language>.toml`, otherwise the builtin packs
+    /// are used.
+    pub directory: Option<&'a Path>,
+
+    /// Values of placeholders that can be used in any message, e.g. the name of the event.
+    pub variables: HashMap<String, String>,
+}
+
+impl Prompts<'_> {
+    /// Loads the prompts for a language, if the pack has any.
+    fn load(&self, code: &str) -> anyhow::Result<Option<PromptPack>> {
+        let contents = match self.directory {
+            Some(directory) => {
+                let path = directory.join(self.pack).join(format!("{code}.toml"));
+                if !path.exists() {
+                    return Ok(None);
+                }
+                std::fs::read_to_string(&path)
+                    .with_context(|| format!("failed to read {}", path.display()))?
+            }
+            None => match PROMPT_PACKS
+                .iter()
+                .find(|(pack, language, _)| *pack == self.pack && *language == code)
+            {
+                Some((_, _, contents)) => contents.to_string(),
+                None => return Ok(None),
+            },
+        };
+
+        let pack = toml::from_str(&contents)
+            .with_context(|| format!("failed to parse {} prompts for {code}", self.pack))?;
+
+        Ok(Some(pack))
+    }
+}
+
+/// Everything said to a caller in one language.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct Language {
+    /// BCP 47 language tag, used for both speech synthesis and recognition.
+    pub code: String,
+
+    /// Name of the language, in the language itself.
+    pub name: String,
+
+    /// Synthesizer voices for the language by vendor, used instead of the configured voice.
+    #[serde(default)]
+    pub voices: HashMap<String, String>,
+
+    /// Fallback synthesizer voices for the language by vendor, used instead of the configured
+    /// fallback voice.
+    #[serde(default)]
+    pub fallback_voices: HashMap<String, String>,
+
+    /// How times of day are read out, if different to the configured style.
+    pub time_style: Option<TimeStyle>,
+
+    /// Whether small numbers are spelled out in English words, otherwise they are left to the
+    /// synthesizer.
+    #[serde(default)]
+    pub spell_numbers: bool,
+
+    /// Names of the days of the week, starting with Monday.
+    pub weekdays: [String; 7],
+
+    /// What callers may say instead of pressing keys.
+    pub speech: Keywords,
+
+    messages: HashMap<String, Message>,
+
+    #[serde(skip)]
+    variables: HashMap<String, String>,
+}
+
+impl Language {
+    /// Checks whether the catalog has a message.
+    pub(crate) fn contains(&self, id: &str) -> bool {
+        self.messages.contains_key(id)
+    }
+
+    /// Gets a message that has no placeholders.
+    pub(crate) fn message(&self, id: &str) -> Speech {
+        self.format(id, &[])
+    }
+
+    /// Gets a message, replacing each `{name}` placeholder with the corresponding argument or
+    /// variable.
+    pub(crate) fn format(&self, id: &str, args: &[(&str, Speech)]) -> Speech {
+        let template = match self.messages.get(id) {
+            Some(Message::One(template)) => Some(template),
+            Some(Message::Variants(variants)) => variants.choose(&mut rand::rng()),
+            None => None,
+        };
+        let Some(template) = template else {
+            error!("Message {id} is missing from the {} catalog", self.code);
+            return Speech::from(id);
+        };
+
+        let mut speech = Speech::new();
+        let mut rest = template.as_str();
+
+        while let Some(start) = rest.find('{') {
+            let Some(end) = rest[start..].find('}').map(|end| start + end) else {
+                break;
+            };
+
+            let name = &rest[start + 1..end];
+            let value = args
+                .iter()
+                .find(|(arg, _)| *arg == name)
+                .map(|(_, value)| value.clone())
+                .or_else(|| self.variables.get(name).map(Speech::from));
+
+            match value {
+                Some(value) => {
+                    speech = speech.text(&rest[..start]).append(value);
+                }
+                None => {
+                    warn!("Message {id} has no argument for placeholder {name}");
+                    speech = speech.text(&rest[..=end]);
+                }
+            }
+
+            rest = &rest[end + 1..];
+        }
+
+        speech.text(rest)
+    }
+}
+
+/// The languages callers can choose between.
+pub(crate) struct Catalog {
+    languages: Vec<Arc<Language>>,
+}
+
+impl Catalog {
+    /// Loads the catalogs of the given languages, the first of which is the default, along with
+    /// their prompts.
+    ///
+    /// Messages missing from a catalog are taken from the default language.
+    pub(crate) fn load(codes: &[String], prompts: &Prompts) -> anyhow::Result<Self> {
+        let available = LANGUAGES
+            .iter()
+            .map(|contents| toml::from_str(contents))
+            .collect::<Result<Vec<Language>, _>>()?;
+
+        let mut languages = Vec::new();
+        for code in codes {
+            let Some(language) = available.iter().find(|language| language.code == *code) else {
+                anyhow::bail!(
+                    "no catalog for language {code}, available languages are: {}",
+                    available
+                        .iter()
+                        .map(|language| language.code.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+            };
+
+            let mut language = language.clone();
+            match prompts.load(code)? {
+                Some(pack) => language.messages.extend(pack.messages),
+                None if languages.is_empty() => {
+                    anyhow::bail!("prompt pack {} has no prompts for {code}", prompts.pack)
+                }
+                None => warn!("Prompt pack {} has no prompts for {code}", prompts.pack),
+            }
+            language.variables = prompts.variables.clone();
+
+            languages.push(language);
+        }
+
+        let Some((default, others)) = languages.split_first_mut() else {
+            anyhow::bail!("at least one language is required");
+        };
+        for language in others {
+            for (id, message) in &default.messages {
+                if !language.messages.contains_key(id) {
+                    warn!("Message {id} is missing from the {} catalog", language.code);
+                    language.messages.insert(id.clone(), message.clone());
+                }
+            }
+        }
+
+        info!(
+            "Offering languages: {} ({} prompts)",
+            languages
+                .iter()
+                .map(|language| format!("{} ({})", language.name, language.code))
+                .collect::<Vec<_>>()
+                .join(", "),
+            prompts.pack
+        );
+
+        Ok(Self {
+            languages: languages.into_iter().map(Arc::new).collect(),
+        })
+    }
+
+    pub(crate) fn languages(&self) -> &[Arc<Language>] {
+        &self.languages
+    }
+
+    /// Gets a language by its code, or the default language if there is no such language.
+    pub(crate) fn get(&self, code: Option<&str>) -> Arc<Language> {
+        code.and_then(|code| self.languages.iter().find(|language| language.code == code))
+            .unwrap_or(&self.languages[0])
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures, lexicon::Lexicon};
+    use std::collections::BTreeSet;
+
+    fn text(speech: Speech) -> String {
+        speech.render(false, &Lexicon::default())
+    }
+
+    /// Loads English and German with a prompt pack written for the test, in which German is
+    /// missing some messages.
+    fn catalog() -> Catalog {
+        let directory = fixtures::temp_path("prompts");
+        std::fs::create_dir_all(directory.join("test")).unwrap();
+        std::fs::write(
+            directory.join("test/en-GB.toml"),
+            r#"
+            [messages]
+            welcome = "Welcome to {event}, {name}."
+            unknown = "Welcome to {venue}."
+            unclosed = "Welcome to {event"
+            variants = ["One", "Two", "Three"]
+            english-only = "Only in English."
+            "#,
+        )
+        .unwrap();
+        std::fs::write(
+            directory.join("test/de-DE.toml"),
+            r#"
+            [messages]
+            welcome = "Willkommen bei {event}, {name}."
+            "#,
+        )
+        .unwrap();
+
+        let prompts = Prompts {
+            pack: "test",
+            directory: Some(&directory),
+            variables: [("event".to_string(), "EMF".to_string())].into(),
+        };
+        Catalog::load(&["en-GB".to_string(), "de-DE".to_string()], &prompts).unwrap()
+    }
+
+    #[test]
+    fn placeholders() {
+        let english = catalog().get(Some("en-GB"));
+
+        assert_eq!(
+            text(english.format("welcome", &[("name", "Alice".into())])),
+            "Welcome to EMF, Alice."
+        );
+        assert_eq!(
+            text(english.format(
+                "welcome",
+                &[("name", "Alice".into()), ("event", "BornHack".into())]
+            )),
+            "Welcome to BornHack, Alice."
+        );
+
+        // Placeholders with no value, and braces that are not placeholders, are left as they are
+        assert_eq!(text(english.message("welcome")), "Welcome to EMF, {name}.");
+        assert_eq!(text(english.message("unknown")), "Welcome to {venue}.");
+        assert_eq!(text(english.message("unclosed")), "Welcome to {event");
+    }
+
+    #[test]
+    fn variants() {
+        let english = catalog().get(None);
+
+        let said: BTreeSet<String> = (0..100)
+            .map(|_| text(english.message("variants")))
+            .collect();
+        assert_eq!(
+            said.iter().map(String::as_str).collect::<Vec<_>>(),
+            ["One", "Three", "Two"]
+        );
+    }
+
+    #[test]
+    fn missing_messages_come_from_the_default_language() {
+        let catalog = catalog();
+        let german = catalog.get(Some("de-DE"));
+
+        assert_eq!(
+            text(german.format("welcome", &[("name", "Alice".into())])),
+            "Willkommen bei EMF, Alice."
+        );
+        assert_eq!(text(german.message("english-only")), "Only in English.");
+        assert!(german.contains("english-only"));
+
+        // A message missing from every language is said as its ID
+        assert_eq!(text(german.message("nonexistent")), "nonexistent");
+        assert!(!german.contains("nonexistent"));
+    }
+
+    #[test]
+    fn languages() {
+        let catalog = catalog();
+
+        assert_eq!(catalog.get(None).code, "en-GB");
+        assert_eq!(catalog.get(Some("de-DE")).code, "de-DE");
+        assert_eq!(catalog.get(Some("fr-FR")).code, "en-GB");
+
+        let prompts = Prompts {
+            pack: "serious",
+            directory: None,
+            variables: HashMap::new(),
+        };
+        assert!(Catalog::load(&["fr-FR".to_string()], &prompts).is_err());
+        assert!(Catalog::load(&[], &prompts).is_err());
+    }
+}
@@ -0,0 +1,99 @@
+use chrono::{DateTime, Duration, FixedOffset, Utc};
+use std::time::Instant;
+
+/// Source of the current time, as far as the schedule is concerned.
+pub(crate) trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<FixedOffset>;
+}
+
+/// The actual current time.
+pub(crate) struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<FixedOffset> {
+        Utc::now().into()
+    }
+}
+
+/// A time that never changes, useful for hearing exactly what a caller would at a given moment.
+pub(crate) struct FixedClock {
+    now: DateTime<FixedOffset>,
+}
+
+impl FixedClock {
+    pub(crate) fn new(now: DateTime<FixedOffset>) -> Self {
+        Self { now }
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<FixedOffset> {
+        self.now
+    }
+}
+
+/// A time that starts from a given point when the application starts, and then advances at a
+/// given rate relative to real time (e.g. a rate of 60 makes every real minute an hour).
+pub(crate) struct RehearsalClock {
+    start: DateTime<FixedOffset>,
+    started: Instant,
+    rate: f64,
+}
+
+impl RehearsalClock {
+    pub(crate) fn new(start: DateTime<FixedOffset>, rate: f64) -> Self {
+        Self {
+            start,
+            started: Instant::now(),
+            rate,
+        }
+    }
+}
+
+impl Clock for RehearsalClock {
+    fn now(&self) -> DateTime<FixedOffset> {
+        std::time::Duration::try_from_secs_f64(self.started.elapsed().as_secs_f64() * self.rate)
+            .ok()
+            .and_then(|elapsed| Duration::from_std(elapsed).ok())
+            .and_then(|elapsed| self.start.checked_add_signed(elapsed))
+            // Stop at the end of time rather than wrapping around or panicking
+            .unwrap_or(DateTime::<Utc>::MAX_UTC.into())
+    }
+}
+
+/// Checks that a clock rate is a finite, positive number.
+pub(crate) fn parse_rate(rate: &str) -> Result<f64, String> {
+    match rate.parse::<f64>() {
+        Ok(rate) if rate.is_finite() && rate > 0.0 => Ok(rate),
+        Ok(_) => Err("must be a finite number greater than zero".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{timestamp, NOW};
+
+    #[test]
+    fn rate() {
+        assert_eq!(parse_rate("60"), Ok(60.0));
+        assert_eq!(parse_rate("0.5"), Ok(0.5));
+
+        for rate in ["0", "-1", "inf", "NaN", "fast"] {
+            assert!(parse_rate(rate).is_err(), "{rate}");
+        }
+    }
+
+    #[test]
+    fn rehearsal_clock_does_not_overflow() {
+        let start = timestamp(NOW);
+
+        let clock = RehearsalClock::new(start, 1.0);
+        assert!(clock.now() >= start);
+
+        let clock = RehearsalClock::new(start, f64::MAX);
+        std::thread::sleep(std::time::Duration::from_millis(1));
+        assert_eq!(clock.now(), DateTime::<Utc>::MAX_UTC);
+    }
+}
@@ -0,0 +1,288 @@
+use crate::{
+    jambonz::{Hangup, Redirect, Verb},
+    route::Route,
+    signature::read_body,
+    voice::PromptKind,
+    AppState,
+};
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures_util::FutureExt;
+use metrics::counter;
+use serde_json::{Map, Value};
+use std::{any::Any, panic::AssertUnwindSafe};
+use tracing::error;
+
+/// Ensures jambonz always gets verbs back, so that the caller never hears dead air.
+///
+/// Requests that panic, are rejected by an extractor or do not match a route are answered with an
+/// apology, after which the caller is returned to the menu (or hung up on, if the menu is what
+/// failed).
+pub(crate) async fn recover(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let (parts, body) = request.into_parts();
+
+    // The payload is kept so that it can be logged if the request fails
+    let body = match read_body(body).await {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+
+    let path = parts.uri.path().to_string();
+    let request = Request::from_parts(parts, Body::from(body.clone()));
+
+    let (kind, reason) = match AssertUnwindSafe(next.run(request)).catch_unwind().await {
+        Ok(response) if response.status().is_success() => return response,
+        // The body of a rejection is not logged, as it can quote the payload
+        Ok(response) => match response.status() {
+            StatusCode::NOT_FOUND => ("not_found", StatusCode::NOT_FOUND.to_string()),
+            status if status.is_client_error() => ("rejection", status.to_string()),
+            status => ("error", status.to_string()),
+        },
+        Err(panic) => ("panic", panic_message(panic.as_ref())),
+    };
+
+    let payload = serde_json::from_slice::<Map<String, Value>>(&body).ok();
+
+    error!(
+        "Request to {path} failed ({kind}: {reason}) with {}",
+        describe(payload.as_ref(), body.len())
+    );
+    counter!(crate::METRIC_HANDLER_ERRORS_NAME, "kind" => kind).increment(1);
+
+    Json(apology(&state, &path, payload.as_ref())).into_response()
+}
+
+/// Describes a payload well enough to debug a failed request, without logging the values in it
+/// (which include the numbers of the caller and callee).
+fn describe(payload: Option<&Map<String, Value>>, len: usize) -> String {
+    match payload {
+        Some(payload) => format!(
+            "payload for call {} with fields {}",
+            payload
+                .get("call_sid")
+                .and_then(Value::as_str)
+                .unwrap_or("unknown"),
+            payload
+                .keys()
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        None => format!("{len} byte payload that is not a JSON object"),
+    }
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+/// Apologises to the caller in their language, if the payload identifies the call.
+fn apology(state: &AppState, path: &str, payload: Option<&Map<String, Value>>) -> Vec<Verb> {
+    let session = payload
+        .and_then(|payload| payload.get("call_sid")?.as_str())
+        .and_then(|call_sid| state.sessions.get(call_sid))
+        .unwrap_or_default();
+    let language = state.catalog.get(session.language.as_deref());
+    let voice = state.voice.for_language(&language);
+
+    let mut verbs =
+        vec![voice.speak_verb_as(PromptKind::Error, language.message("internal-error"))];
+
+    match Route::from_hook(path) {
+        // Returning to the menu would only fail again
+        Some(Route::Incoming | Route::Menu) => {
+            verbs.push(voice.speak_verb(language.message("goodbye")));
+            verbs.push(Verb::Hangup(Hangup::default()));
+        }
+        _ => verbs.push(Verb::Redirect(Redirect {
+            action_hook: state.hooks.get(Route::Menu),
+        })),
+    }
+
+    verbs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fixtures::{self, verbs},
+        signature::MAX_BODY_BYTES,
+    };
+    use axum::{routing::post, Router};
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+    use tracing_subscriber::fmt::MakeWriter;
+
+    /// Collects everything logged while it is the default subscriber.
+    #[derive(Clone, Default)]
+    struct Log(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Log {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> MakeWriter<'a> for Log {
+        type Writer = Self;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    async fn call(app: Router<AppState>, path: &str, body: impl Into<Body>) -> (StatusCode, Value) {
+        let state = fixtures::state();
+        let app = app
+            .layer(axum::middleware::from_fn_with_state(state.clone(), recover))
+            .with_state(state);
+
+        fixtures::post(app, path, body).await
+    }
+
+    async fn panics() -> &'static str {
+        panic!("something went wrong")
+    }
+
+    fn call_details() -> String {
+        json!({
+            "call_sid": "abc",
+            "from": "4000",
+            "to": "8000",
+        })
+        .to_string()
+    }
+
+    #[tokio::test]
+    async fn panic() {
+        let app = Router::new().route(Route::EventsNow.path(), post(panics));
+
+        let (status, response) = call(app, Route::EventsNow.path(), call_details()).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(verbs(&response), ["say", "redirect"]);
+        assert_eq!(response[1]["actionHook"], "/call/menu");
+    }
+
+    #[tokio::test]
+    async fn panic_in_menu() {
+        let app = Router::new().route(Route::Menu.path(), post(panics));
+
+        let (status, response) = call(app, Route::Menu.path(), call_details()).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(verbs(&response), ["say", "say", "hangup"]);
+    }
+
+    #[tokio::test]
+    async fn json_rejection() {
+        let (status, response) = call(
+            crate::handlers::build_router(),
+            Route::EventsNow.path(),
+            r#"{"not":"a call"}"#,
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(verbs(&response), ["say", "redirect"]);
+    }
+
+    #[tokio::test]
+    async fn rejected_payload_is_not_logged() {
+        let log = Log::default();
+        let subscriber = tracing_subscriber::fmt()
+            .with_ansi(false)
+            .with_writer(log.clone())
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        // The rejection describes the mistyped value, which here is the caller's number
+        let (status, response) = call(
+            crate::handlers::build_router(),
+            Route::EventsNow.path(),
+            r#"{"call_sid":447700900123,"from":"+447700900123"}"#,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(verbs(&response), ["say", "redirect"]);
+
+        let log = String::from_utf8(log.0.lock().unwrap().clone()).unwrap();
+        assert!(
+            log.contains("failed (rejection: 422 Unprocessable Entity)"),
+            "{log}"
+        );
+        assert!(!log.contains("447700900123"), "{log}");
+    }
+
+    #[tokio::test]
+    async fn unknown_path() {
+        let (status, response) = call(
+            crate::handlers::build_router(),
+            "/call/nothing_here",
+            call_details(),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(verbs(&response), ["say", "redirect"]);
+    }
+
+    #[tokio::test]
+    async fn success_is_untouched() {
+        let app = Router::new().route(Route::EventsNow.path(), post(|| async { "[]" }));
+
+        let (status, response) = call(app, Route::EventsNow.path(), call_details()).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(response, json!([]));
+    }
+
+    #[test]
+    fn payload_values_are_not_described() {
+        let payload = serde_json::from_str(&call_details()).unwrap();
+        let description = describe(Some(&payload), 0);
+
+        assert_eq!(
+            description,
+            "payload for call abc with fields call_sid, from, to"
+        );
+        assert_eq!(
+            describe(None, 12),
+            "12 byte payload that is not a JSON object"
+        );
+    }
+
+    #[tokio::test]
+    async fn large_body() {
+        let (status, _) = call(
+            crate::handlers::build_router(),
+            "/call/nothing_here",
+            vec![b' '; MAX_BODY_BYTES + 1],
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
@@ -0,0 +1,162 @@
+//! Shared setup for tests: the builtin catalogs, a voice, a small schedule, application state built
+//! from them and requests made to the router with it.
+
+use crate::{
+    cache, callers,
+    catalog::{Catalog, Language, Prompts},
+    clock, lexicon, menu, phrasing, readout, session, voice, AppState,
+};
+use axum::{body::Body, extract::Request, http::StatusCode, Router};
+use chrono::{DateTime, FixedOffset};
+use emfcamp_schedule_api::schedule::{
+    event::{Event, Kind},
+    Schedule,
+};
+use serde_json::Value;
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tower::ServiceExt;
+
+/// Time that the clock of [`state`] is pinned to, during the schedule of [`schedule`].
+pub(crate) const NOW: &str = "2024-06-01T12:00:00+01:00";
+
+pub(crate) fn timestamp(s: &str) -> DateTime<FixedOffset> {
+    DateTime::parse_from_rfc3339(s).unwrap()
+}
+
+/// The builtin catalogs of the given languages, with the serious prompts.
+pub(crate) fn catalog(codes: &[&str]) -> Catalog {
+    let prompts = Prompts {
+        pack: "serious",
+        directory: None,
+        variables: [("event".to_string(), "EMF".to_string())].into(),
+    };
+    let codes: Vec<String> = codes.iter().map(|code| code.to_string()).collect();
+
+    Catalog::load(&codes, &prompts).unwrap()
+}
+
+pub(crate) fn language(code: &str) -> Arc<Language> {
+    catalog(&[code]).get(None)
+}
+
+pub(crate) fn voice() -> voice::Voice {
+    voice::Voice {
+        ssml: false,
+        lexicon: Arc::new(lexicon::LexiconStore::builtin()),
+        synthesizer: Default::default(),
+        overrides: Default::default(),
+        recognizer: Default::default(),
+    }
+}
+
+pub(crate) fn event(id: u32, title: &str, venue: &str, start: &str, end: &str) -> Event {
+    Event {
+        id,
+        start: timestamp(start),
+        end: timestamp(end),
+        venue: venue.to_string(),
+        title: title.to_string(),
+        speaker: "Someone".to_string(),
+        description: String::new(),
+        kind: Kind::Talk,
+    }
+}
+
+/// Talks on two stages, some of which are on at [`NOW`].
+pub(crate) fn schedule() -> Schedule {
+    let mut events = Vec::new();
+    for (i, hour) in (10..18).enumerate() {
+        for (j, venue) in ["Stage A", "Stage B"].into_iter().enumerate() {
+            events.push(event(
+                (i * 2 + j) as u32,
+                &format!("Talk {} at {venue}", i + 1),
+                venue,
+                &format!("2024-06-01T{hour}:00:00+01:00"),
+                &format!("2024-06-01T{}:00:00+01:00", hour + 1),
+            ));
+        }
+    }
+
+    Schedule { events }
+}
+
+/// A path in the temporary directory that no other test uses.
+pub(crate) fn temp_path(name: &str) -> PathBuf {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+    std::env::temp_dir().join(format!(
+        "dial-a-schedule-{}-{}-{name}",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+/// Writes a schedule to a temporary file, for use as a schedule source.
+pub(crate) fn schedule_file(schedule: &Schedule) -> PathBuf {
+    let path = temp_path("schedule.json");
+    std::fs::write(&path, serde_json::to_vec(schedule).unwrap()).unwrap();
+    path
+}
+
+/// State with the builtin menu and prompts, the clock pinned to [`NOW`] and [`schedule`] as the
+/// schedule.
+pub(crate) fn state() -> AppState {
+    AppState {
+        active_calls: Arc::new(session::ActiveCalls::new(Duration::from_secs(60))),
+        callers: Arc::new(callers::Callers::new(Vec::new(), None)),
+        catalog: Arc::new(catalog(&["en-GB"])),
+        clock: Arc::new(clock::FixedClock::new(timestamp(NOW))),
+        hooks: Default::default(),
+        menu: Arc::new(menu::Menu::load(None).unwrap()),
+        phrasing: phrasing::Phrasing {
+            timezone: chrono_tz::Europe::London,
+            time_style: phrasing::TimeStyle::Natural,
+        },
+        readouts: Arc::new(readout::Readouts::load(None, "en-GB").unwrap()),
+        schedule: Arc::new(cache::ScheduleCache::new(
+            cache::ScheduleSource::file(schedule_file(&schedule())),
+            None,
+        )),
+        sessions: Arc::new(session::InMemorySessionStore::new(Duration::from_secs(60))),
+        voice: voice(),
+        menu_max_reprompts: 2,
+        events_per_page: 4,
+    }
+}
+
+/// Makes a JSON request to a router, giving the status and the JSON response (or null if the
+/// response is not JSON).
+pub(crate) async fn post(app: Router, path: &str, body: impl Into<Body>) -> (StatusCode, Value) {
+    let response = app
+        .oneshot(
+            Request::post(path)
+                .header("content-type", "application/json")
+                .body(body.into())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+/// Names of the verbs in a response.
+pub(crate) fn verbs(response: &Value) -> Vec<&str> {
+    response
+        .as_array()
+        .expect("response should be a list of verbs")
+        .iter()
+        .map(|verb| verb["verb"].as_str().unwrap())
+        .collect()
+}
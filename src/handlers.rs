@@ -1,175 +1,737 @@
 use crate::{
-    jambonz::{Gather, GatherInputs, GatherResponse, Redirect, Verb},
-    mutators::{EventIsPerformance, EventIsTalk, EventIsWorkshop, EventsHappeningNow},
+    cache::FailureKind,
+    catalog::Language,
+    jambonz::{
+        CallDetails, CallStatus, Gather, GatherInputs, GatherReason, GatherResponse, Hangup,
+        Redirect, Verb,
+    },
+    menu::Selection,
+    mutators::{
+        EventAtVenue, EventIsPerformance, EventIsTalk, EventIsWorkshop, EventsHappeningNow,
+    },
+    readout::Readout,
+    route::Route,
+    session::{Session, Stage},
+    speech::{Intent, Navigation},
+    ssml::{Speech, LIST_ITEM_BREAK_MS},
+    voice::{PromptKind, Voice},
     AppState,
 };
 use axum::{
-    extract::State,
+    extract::{Query, State},
+    http::{StatusCode, Uri},
     response::{IntoResponse, Response},
-    routing::post,
+    routing::{post, MethodRouter},
     Json, Router,
 };
-use chrono::{DateTime, Duration, FixedOffset, Utc};
+use chrono::{DateTime, Duration, FixedOffset};
 use emfcamp_schedule_api::schedule::{
     event::Event,
     mutation::{Mutators, SortedByStartTime, StartsAfter, StartsBefore},
     Schedule,
 };
-use metrics::counter;
-use tracing::{error, info};
+use metrics::{counter, histogram};
+use serde::Deserialize;
+use std::sync::Arc;
+use strum::IntoEnumIterator;
+use tracing::{error, info, warn};
 
+/// Builds the webhook router, with a handler for every route.
 pub(super) fn build_router() -> Router<AppState> {
-    Router::new()
-        .route("/call_status", post(call_status))
-        .route("/call/incoming", post(call_incoming))
-        .route("/call/menu", post(call_menu))
-        .route("/call/menu_selection", post(call_menu_selection))
-        .route("/call/events_now", post(call_events_now))
-        .route(
-            "/call/events_starting_soon",
-            post(call_events_starting_soon),
-        )
-        .route(
-            "/call/next_events_everywhere",
-            post(call_next_events_everywhere),
-        )
-        .route(
-            "/call/upcoming_talks_summary",
-            post(call_upcoming_talks_summary),
-        )
-        .route(
-            "/call/upcoming_workshops_summary",
-            post(call_upcoming_workshops_summary),
-        )
-        .route(
-            "/call/upcoming_performances_summary",
-            post(call_upcoming_performances_summary),
-        )
+    Route::iter()
+        .fold(Router::new(), |router, route| {
+            router.route(route.path(), handler(route))
+        })
+        // A fallback of its own means unknown routes still get layers when nested under a base path
+        .fallback(|| async { StatusCode::NOT_FOUND })
+}
+
+fn handler(route: Route) -> MethodRouter<AppState> {
+    match route {
+        Route::CallStatus => post(call_status),
+        Route::Incoming => post(call_incoming),
+        Route::LanguageSelection => post(call_language_selection),
+        Route::Menu => post(call_menu),
+        Route::MenuSelection => post(call_menu_selection),
+        Route::Query => post(call_query),
+        Route::Navigation => post(call_navigation),
+        Route::EventsNow => post(call_events_now),
+        Route::EventsStartingSoon => post(call_events_starting_soon),
+        Route::NextEventsEverywhere => post(call_next_events_everywhere),
+        Route::NextEventsAtVenue => post(call_next_events_at_venue),
+        Route::UpcomingTalksSummary => post(call_upcoming_talks_summary),
+        Route::UpcomingWorkshopsSummary => post(call_upcoming_workshops_summary),
+        Route::UpcomingPerformancesSummary => post(call_upcoming_performances_summary),
+    }
 }
 
 #[axum::debug_handler]
-async fn call_status(Json(status): Json<crate::jambonz::CallStatusDetails>) {
-    info!("Call status: {:?}", status);
+async fn call_status(
+    State(state): State<AppState>,
+    Json(status): Json<crate::jambonz::CallStatusDetails>,
+) {
+    // The caller's number is deliberately left out of logs and metrics
+    let class = state.callers.classify(&status.from).to_string();
+    info!(
+        "Call {} status: {:?} ({class} caller)",
+        status.call_sid, status.call_status
+    );
+
+    let ended = state
+        .active_calls
+        .update(&status.call_sid, &status.call_status);
+
+    match status.call_status {
+        CallStatus::Completed | CallStatus::Failed | CallStatus::Busy | CallStatus::NoAnswer => {
+            state.sessions.remove(&status.call_sid);
+        }
+        _ => state.sessions.update(&status.call_sid, |_| ()),
+    }
+
+    // Counted once per call, however many times jambonz reports that it has ended
+    if let Some(stage) = ended {
+        state.callers.record(&status.from);
+
+        if let CallStatus::Completed = status.call_status {
+            if let Some(duration) = status.duration {
+                histogram!(crate::METRIC_CALL_DURATION_NAME).record(duration as f64);
+            }
+
+            counter!(crate::METRIC_CALL_FUNNEL_NAME, "stage" => stage.label()).increment(1);
+        }
+    }
 
     let call_status = format!("{:?}", status.call_status);
-    counter!(crate::METRIC_CALLS_NAME, "status" => call_status, "from" => status.from).increment(1);
+    counter!(crate::METRIC_CALLS_NAME, "status" => call_status, "class" => class).increment(1);
 }
 
 #[axum::debug_handler]
-async fn call_incoming() -> Response {
+async fn call_incoming(State(state): State<AppState>, Json(call): Json<CallDetails>) -> Response {
     info!("Incomming call");
     counter!(crate::METRIC_REQUESTS_NAME, "endpoint" => "incoming").increment(1);
 
-    let verbs = vec![
-        crate::voice::speak_verb("Hello, and welcome to Dial-a-Schedule."),
-        Verb::Redirect(Redirect {
-            action_hook: "/call/menu".into(),
-        }),
-    ];
+    state.sessions.put(&call.call_sid, Session::default());
+
+    let caller = Caller::new(&state, &call.call_sid);
+
+    let mut verbs = vec![caller
+        .voice
+        .speak_verb_as(PromptKind::Announcement, caller.language.message("welcome"))];
+
+    let languages = state.catalog.languages();
+
+    if languages.len() > 1 {
+        // Each option is read out in its own language, so that callers recognise theirs
+        let options = languages.iter().enumerate().map(|(i, language)| {
+            state.voice.for_language(language).speak_verb(
+                language.format("language-option", &[("digit", (i + 1).to_string().into())]),
+            )
+        });
+        verbs.extend(options);
+
+        verbs.push(Verb::Gather(Gather {
+            action_hook: state.hooks.get(Route::LanguageSelection),
+            input: vec![GatherInputs::Digits],
+            num_digits: Some(1),
+            timeout: Some(5),
+            ..Default::default()
+        }));
+    } else {
+        verbs.push(Verb::Redirect(Redirect {
+            action_hook: state.hooks.get(Route::Menu),
+        }));
+    }
 
     Json(verbs).into_response()
 }
 
 #[axum::debug_handler]
-async fn call_menu() -> Response {
-    info!("Menu");
-    counter!(crate::METRIC_REQUESTS_NAME, "endpoint" => "menu").increment(1);
+async fn call_language_selection(
+    State(state): State<AppState>,
+    Json(payload): Json<GatherResponse>,
+) -> Response {
+    info!("Language selection: {:?}", payload);
+    counter!(crate::METRIC_REQUESTS_NAME, "endpoint" => "language_selection").increment(1);
+
+    let language = payload
+        .digits
+        .as_deref()
+        .and_then(|digits| digits.parse::<usize>().ok())
+        .and_then(|digit| digit.checked_sub(1))
+        .and_then(|i| state.catalog.languages().get(i));
+
+    // Callers who do not choose get the default language
+    if let Some(language) = language {
+        info!("Caller chose {}", language.code);
+        state.sessions.update(&payload.call_sid, |session| {
+            session.language = Some(language.code.clone())
+        });
+    }
+
+    Json(vec![Verb::Redirect(Redirect {
+        action_hook: state.hooks.get(Route::Menu),
+    })])
+    .into_response()
+}
+
+/// A call, with the language the caller has chosen and a voice that speaks it.
+struct Caller {
+    call_sid: String,
+    language: Arc<Language>,
+    voice: Voice,
+}
 
-    let verbs = vec![Verb::Gather(Gather {
-        action_hook: "/call/menu_selection".to_string(),
-        input: vec![GatherInputs::Digits],
+impl Caller {
+    fn new(state: &AppState, call_sid: &str) -> Self {
+        let session = state.sessions.get(call_sid).unwrap_or_default();
+        let language = state.catalog.get(session.language.as_deref());
+        let voice = state.voice.for_language(&language);
+
+        Self {
+            call_sid: call_sid.to_string(),
+            language,
+            voice,
+        }
+    }
+
+    /// Speaks a message from the caller's catalog.
+    fn say(&self, id: &str) -> Verb {
+        self.voice.speak_verb(self.language.message(id))
+    }
+}
+
+/// Records that the caller has reached a part of the call flow they may later want to go back to.
+fn visit(state: &AppState, call_sid: &str, uri: &Uri) {
+    state
+        .sessions
+        .update(call_sid, |session| session.visit(&uri.to_string()));
+}
+
+/// Records that the caller has reached a stage of the call flow.
+fn reach(state: &AppState, call_sid: &str, stage: Stage) {
+    state.active_calls.reach(call_sid, stage);
+}
+
+/// Builds a Gather that accepts either a keypress or speech, with recognizer hints taken from the
+/// current schedule.
+async fn gather_digits_or_speech(
+    state: &AppState,
+    caller: &Caller,
+    path: &str,
+    prompt: Speech,
+) -> Verb {
+    let schedule = match state.schedule.get().await {
+        Ok(cached) => Some(cached.schedule),
+        Err(e) => {
+            warn!("Schedule API error, speech hints will be limited: {e}");
+            None
+        }
+    };
+
+    Verb::Gather(Gather {
+        action_hook: menu_hook(state, Route::MenuSelection, path),
+        input: vec![GatherInputs::Digits, GatherInputs::Speech],
         num_digits: Some(1),
-        recognizer: None,
-        say: Some(crate::voice::speak(
-            "Dial 1 to hear what's going on right now. Need something to do? Dial 2 to hear what events are starting soon. Dial 3 to hear what is happening next at each venue. Dial 4 to get a summary of upcoming talks, dial 5 to get a summary of upcoming workshops, or dial 6 to get a summary of performances.",
+        recognizer: Some(caller.voice.recognizer(
+            crate::speech::hints(schedule.as_ref(), &caller.language.speech),
+            &caller.language,
         )),
-    })];
+        say: Some(caller.voice.speak(prompt)),
+        ..Default::default()
+    })
+}
+
+/// The hook for a menu, or a selection from it, given the digits pressed to reach the menu.
+fn menu_hook(state: &AppState, route: Route, path: &str) -> String {
+    if path.is_empty() {
+        state.hooks.get(route)
+    } else {
+        state.hooks.with_query(route, &[("path", path)])
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct MenuQuery {
+    /// Digits pressed to reach a submenu from the main menu.
+    #[serde(default)]
+    path: String,
+}
+
+#[axum::debug_handler]
+async fn call_menu(
+    State(state): State<AppState>,
+    Query(query): Query<MenuQuery>,
+    uri: Uri,
+    Json(call): Json<CallDetails>,
+) -> Response {
+    visit(&state, &call.call_sid, &uri);
+    reach(&state, &call.call_sid, Stage::Menu);
+    let session = state.sessions.get(&call.call_sid).unwrap_or_default();
+
+    info!("Menu {:?} (retry {})", query.path, session.retries);
+    counter!(crate::METRIC_REQUESTS_NAME, "endpoint" => "menu").increment(1);
+
+    let caller = Caller::new(&state, &call.call_sid);
+
+    // Get a little more insistent each time the caller says nothing
+    let preamble = match session.retries {
+        0 => Speech::new(),
+        1 => caller.language.message("menu-first-reprompt"),
+        _ => caller.language.message("menu-reprompt"),
+    };
+
+    let (path, menu) = match state.menu.submenu(&query.path) {
+        Some(menu) => (query.path.as_str(), menu),
+        None => {
+            warn!("No menu at {:?}, using the main menu", query.path);
+            ("", state.menu.as_ref())
+        }
+    };
+
+    let mut prompt = preamble.append(menu.prompt(&caller.language));
+    if path.is_empty() {
+        prompt = prompt.append(caller.language.message("menu-speech"));
+    }
+
+    let verbs = vec![gather_digits_or_speech(&state, &caller, path, prompt).await];
+
+    Json(verbs).into_response()
+}
+
+#[axum::debug_handler]
+async fn call_query(
+    State(state): State<AppState>,
+    uri: Uri,
+    Json(call): Json<CallDetails>,
+) -> Response {
+    visit(&state, &call.call_sid, &uri);
+    info!("Query");
+    counter!(crate::METRIC_REQUESTS_NAME, "endpoint" => "query").increment(1);
+
+    let caller = Caller::new(&state, &call.call_sid);
+
+    let verbs =
+        vec![gather_digits_or_speech(&state, &caller, "", caller.language.message("query")).await];
 
     Json(verbs).into_response()
 }
 
 #[axum::debug_handler]
-async fn call_menu_selection(Json(payload): Json<GatherResponse>) -> Response {
+async fn call_menu_selection(
+    State(state): State<AppState>,
+    Query(query): Query<MenuQuery>,
+    Json(payload): Json<GatherResponse>,
+) -> Response {
     info!("Menu selection: {:?}", payload);
     counter!(crate::METRIC_REQUESTS_NAME, "endpoint" => "menu_selection").increment(1);
 
-    let digits = payload.digits.unwrap();
-
-    let redirect_to = match digits.as_str() {
-        "1" => Some("/call/events_now"),
-        "2" => Some("/call/events_starting_soon"),
-        "3" => Some("/call/next_events_everywhere"),
-        "4" => Some("/call/upcoming_talks_summary"),
-        "5" => Some("/call/upcoming_workshops_summary"),
-        "6" => Some("/call/upcoming_performances_summary"),
-        _ => None,
+    let digits = payload
+        .digits
+        .as_deref()
+        .filter(|digits| !digits.is_empty());
+
+    let caller = Caller::new(&state, &payload.call_sid);
+
+    let verbs = if let Some(digits) = digits {
+        reset_retries(&state, &payload.call_sid);
+        route_digits(&state, &caller, &query.path, digits)
+    } else if let Some((transcript, confidence)) = payload.transcript() {
+        reset_retries(&state, &payload.call_sid);
+        route_speech(&state, &caller, &payload.call_sid, transcript, confidence).await
+    } else {
+        no_input(&state, &caller, &query.path, payload.reason)
     };
 
-    let verbs = match redirect_to {
-        Some(endpoint) => vec![Verb::Redirect(Redirect {
-            action_hook: endpoint.to_string(),
+    Json(verbs).into_response()
+}
+
+fn reset_retries(state: &AppState, call_sid: &str) {
+    state
+        .sessions
+        .update(call_sid, |session| session.retries = 0);
+}
+
+/// Handles a Gather that finished without the caller pressing or saying anything, by repeating the
+/// menu a limited number of times before giving up and hanging up.
+fn no_input(
+    state: &AppState,
+    caller: &Caller,
+    path: &str,
+    reason: Option<GatherReason>,
+) -> Vec<Verb> {
+    let retries = state.sessions.update(&caller.call_sid, |session| {
+        session.retries += 1;
+        session.retries
+    });
+
+    info!("No input received (retry {retries}, reason {reason:?})");
+
+    if retries <= state.menu_max_reprompts {
+        counter!(crate::METRIC_NO_INPUT_NAME, "outcome" => "reprompt").increment(1);
+        vec![Verb::Redirect(Redirect {
+            action_hook: menu_hook(state, Route::Menu, path),
+        })]
+    } else {
+        counter!(crate::METRIC_NO_INPUT_NAME, "outcome" => "hangup").increment(1);
+        vec![
+            caller.voice.speak_verb_as(
+                PromptKind::Announcement,
+                caller.language.message("no-input-goodbye"),
+            ),
+            Verb::Hangup(Hangup::default()),
+        ]
+    }
+}
+
+fn route_digits(state: &AppState, caller: &Caller, path: &str, digits: &str) -> Vec<Verb> {
+    let selection = state
+        .menu
+        .submenu(path)
+        .and_then(|menu| menu.select(path, digits));
+
+    if selection.is_some() {
+        let menu = if path.is_empty() { "main" } else { path };
+        counter!(
+            crate::METRIC_MENU_SELECTIONS_NAME,
+            "menu" => menu.to_string(),
+            "option" => digits.to_string()
+        )
+        .increment(1);
+        reach(state, &caller.call_sid, Stage::Selected);
+    }
+
+    match selection {
+        Some(Selection::Action(endpoint)) => vec![Verb::Redirect(Redirect {
+            action_hook: state.hooks.resolve(endpoint),
+        })],
+        Some(Selection::Submenu(path)) => vec![Verb::Redirect(Redirect {
+            action_hook: menu_hook(state, Route::Menu, &path),
         })],
         None => {
             info!("A user entered an obviously incorrect option");
             counter!(crate::METRIC_USER_ERROR_NAME).increment(1);
             vec![
-                crate::voice::speak_verb(&format!("Yeah, so you know when I gave you those options? The intention is that you pick one of those. Not some nonsense number like {digits}. I am not angry, I am just disappointed. Try again.")),
-                Verb::Redirect(Redirect{ action_hook: "/call/menu".to_string() })
+                caller.voice.speak_verb_as(
+                    PromptKind::Error,
+                    caller
+                        .language
+                        .format("invalid-menu-option", &[("digits", digits.into())]),
+                ),
+                Verb::Redirect(Redirect {
+                    action_hook: menu_hook(state, Route::Menu, path),
+                }),
             ]
         }
+    }
+}
+
+async fn route_speech(
+    state: &AppState,
+    caller: &Caller,
+    call_sid: &str,
+    transcript: &str,
+    confidence: Option<f64>,
+) -> Vec<Verb> {
+    info!("Caller said \"{transcript}\" (confidence {confidence:?})");
+
+    let understood = !confidence.is_some_and(|c| c < crate::speech::MIN_CONFIDENCE);
+
+    let intent = if understood {
+        let venues = match state.schedule.get().await {
+            Ok(cached) => crate::speech::venues(&cached.schedule),
+            Err(e) => {
+                warn!("Schedule API error, venues will not be recognised: {e}");
+                Vec::new()
+            }
+        };
+
+        Intent::from_transcript(transcript, &venues, &caller.language.speech)
+    } else {
+        None
     };
 
-    Json(verbs).into_response()
+    match intent {
+        Some(intent) => {
+            info!("Understood speech as {intent:?}");
+            reach(state, call_sid, Stage::Selected);
+            vec![Verb::Redirect(Redirect {
+                action_hook: intent.action_hook(&state.hooks),
+            })]
+        }
+        None => {
+            info!("Could not understand what a user said");
+            counter!(crate::METRIC_USER_ERROR_NAME).increment(1);
+            vec![
+                caller.say("not-understood"),
+                Verb::Redirect(Redirect {
+                    action_hook: state.hooks.get(Route::Query),
+                }),
+            ]
+        }
+    }
+}
+
+/// Appends the navigation options offered at the end of every piece of content.
+fn with_navigation(
+    state: &AppState,
+    caller: &Caller,
+    mut verbs: Vec<Verb>,
+    more: bool,
+) -> Vec<Verb> {
+    let prompt = if more {
+        "navigation-prompt-with-more"
+    } else {
+        "navigation-prompt"
+    };
+
+    verbs.push(Verb::Gather(Gather {
+        action_hook: state.hooks.get(Route::Navigation),
+        input: vec![GatherInputs::Digits, GatherInputs::Speech],
+        num_digits: Some(1),
+        recognizer: Some(
+            caller
+                .voice
+                .recognizer(Navigation::hints(&caller.language.speech), &caller.language),
+        ),
+        say: Some(caller.voice.speak(caller.language.message(prompt))),
+        ..Default::default()
+    }));
+    verbs
+}
+
+/// Responds with content for the caller, remembering it so that it can be repeated and offering
+/// the navigation options afterwards.
+///
+/// Every endpoint that tells the caller something should respond via this.
+fn respond_with_content(state: &AppState, caller: &Caller, verbs: Vec<Verb>) -> Response {
+    reach(state, &caller.call_sid, Stage::Content);
+
+    let more = state.sessions.update(&caller.call_sid, |session| {
+        session.last_response = verbs.clone();
+        !session.remaining.is_empty()
+    });
+
+    Json(with_navigation(state, caller, verbs, more)).into_response()
+}
+
+/// Takes the first page from a list of items, remembering the rest for when the caller asks for
+/// more.
+///
+/// The remaining items are stored already rendered, so that subsequent pages are consistent with
+/// the first even if the schedule changes in the meantime.
+fn next_page(state: &AppState, caller: &Caller, mut items: Vec<Verb>) -> Vec<Verb> {
+    let remaining = items.split_off(state.events_per_page.max(1).min(items.len()));
+
+    match remaining.len() {
+        0 => {}
+        1 => items.push(caller.say("one-more")),
+        n => items.push(
+            caller.voice.speak_verb(
+                caller
+                    .language
+                    .format("more", &[("count", n.to_string().into())]),
+            ),
+        ),
+    }
+
+    state.sessions.update(&caller.call_sid, |session| {
+        session.remaining = remaining;
+    });
+
+    items
 }
 
-const API_ERROR_MESSAGE: &str = "Oh no, something has gone very wrong. If this keeps happening, please feel free to shout at Dan until it is fixed. Be aware, Dan may shout back, or indeed shout at others as appropriate.";
+#[axum::debug_handler]
+async fn call_navigation(
+    State(state): State<AppState>,
+    Json(payload): Json<GatherResponse>,
+) -> Response {
+    info!("Navigation: {:?}", payload);
+    counter!(crate::METRIC_REQUESTS_NAME, "endpoint" => "navigation").increment(1);
+
+    let session = state.sessions.get(&payload.call_sid).unwrap_or_default();
+    let more = !session.remaining.is_empty();
+    let caller = Caller::new(&state, &payload.call_sid);
+
+    let digits = match (payload.digits.as_deref(), payload.transcript()) {
+        (None, Some((transcript, confidence))) => {
+            info!("Caller said \"{transcript}\" (confidence {confidence:?})");
+            let understood = !confidence.is_some_and(|c| c < crate::speech::MIN_CONFIDENCE);
+
+            match Navigation::from_transcript(transcript, &caller.language.speech)
+                .filter(|_| understood)
+            {
+                Some(Navigation::Repeat) => Some("*"),
+                Some(Navigation::Back) => Some("#"),
+                None => {
+                    info!("Could not understand what a user said");
+                    counter!(crate::METRIC_USER_ERROR_NAME).increment(1);
+                    return Json(with_navigation(
+                        &state,
+                        &caller,
+                        vec![caller.say("not-understood")],
+                        more,
+                    ))
+                    .into_response();
+                }
+            }
+        }
+        (digits, _) => digits,
+    };
+
+    match digits {
+        Some("1") if more => {
+            reach(&state, &payload.call_sid, Stage::MorePages);
+            let verbs = next_page(&state, &caller, session.remaining);
+            respond_with_content(&state, &caller, verbs)
+        }
+        Some("*") => Json(with_navigation(
+            &state,
+            &caller,
+            session.last_response,
+            more,
+        ))
+        .into_response(),
+        Some("#") => {
+            let previous = state.sessions.update(&payload.call_sid, |session| {
+                // The content just read out is the current position
+                session.back()
+            });
+            info!("Going back to {previous:?}");
+            Json(vec![Verb::Redirect(Redirect {
+                action_hook: match previous {
+                    Some(previous) => state.hooks.resolve(&previous),
+                    None => state.hooks.get(Route::Menu),
+                },
+            })])
+            .into_response()
+        }
+        Some("0") => Json(with_navigation(
+            &state,
+            &caller,
+            vec![caller.say("navigation-help")],
+            more,
+        ))
+        .into_response(),
+        Some(digits) if !digits.is_empty() => {
+            info!("A user entered an obviously incorrect option");
+            counter!(crate::METRIC_USER_ERROR_NAME).increment(1);
+            Json(with_navigation(
+                &state,
+                &caller,
+                vec![caller.voice.speak_verb(
+                    caller
+                        .language
+                        .format("invalid-navigation-option", &[("digits", digits.into())]),
+                )],
+                more,
+            ))
+            .into_response()
+        }
+        _ => {
+            info!("No navigation input received (reason {:?})", payload.reason);
+            counter!(crate::METRIC_NO_INPUT_NAME, "outcome" => "hangup").increment(1);
+            Json(vec![
+                caller
+                    .voice
+                    .speak_verb_as(PromptKind::Announcement, caller.language.message("goodbye")),
+                Verb::Hangup(Hangup::default()),
+            ])
+            .into_response()
+        }
+    }
+}
 
 async fn query_and_respond_with_a_list_of_events(
     state: &AppState,
+    caller: &Caller,
     event_filter: impl Fn(Schedule) -> Vec<Event>,
-    negative_response: &str,
-    positive_response: &str,
-    event_to_text: impl Fn(&Event) -> Verb,
+    negative_response: Speech,
+    positive_response: Speech,
+    readout: Readout,
+    now: DateTime<FixedOffset>,
 ) -> Response {
-    let verbs = match state.schedule_client.get_schedule().await {
-        Ok(schedule) => {
-            let events = event_filter(schedule);
+    let (mut verbs, items) = match state.schedule.get().await {
+        Ok(cached) => {
+            let events = event_filter(cached.schedule);
             info!("Got {} events for query", events.len());
+            histogram!(crate::METRIC_RESPONSE_EVENTS_NAME, "readout" => readout.name())
+                .record(events.len() as f64);
 
-            if events.is_empty() {
-                vec![crate::voice::speak_verb(negative_response)]
-            } else {
-                let mut verbs = vec![crate::voice::speak_verb(positive_response)];
+            let mut verbs = Vec::new();
 
-                for event in events {
-                    verbs.push(event_to_text(&event));
-                }
+            if cached.stale {
+                verbs.push(caller.say("stale-schedule"));
+            }
 
-                verbs
+            if events.is_empty() {
+                verbs.push(caller.voice.speak_verb(negative_response));
+                (verbs, Vec::new())
+            } else {
+                verbs.push(caller.voice.speak_verb(positive_response));
+                (
+                    verbs,
+                    events
+                        .iter()
+                        .map(|event| {
+                            let speech = state.readouts.render(
+                                readout,
+                                &caller.language,
+                                &state.phrasing,
+                                event,
+                                now,
+                            );
+                            caller.voice.speak_verb(speech.pause(LIST_ITEM_BREAK_MS))
+                        })
+                        .collect(),
+                )
             }
         }
         Err(e) => {
-            error!("Schedule API error: {e}");
-            counter!(crate::METRIC_API_ERRORS_NAME).increment(1);
-            vec![crate::voice::speak_verb(API_ERROR_MESSAGE)]
+            let kind = FailureKind::classify(&e);
+            error!("Schedule API error ({}): {e}", kind.label());
+
+            // Callers may have better luck shortly if the API could not be reached at all
+            let message = match kind {
+                FailureKind::Timeout | FailureKind::Connection => "api-unreachable",
+                FailureKind::Status(_) | FailureKind::Parse | FailureKind::Other => "api-error",
+            };
+
+            (
+                vec![caller
+                    .voice
+                    .speak_verb_as(PromptKind::Error, caller.language.message(message))],
+                Vec::new(),
+            )
         }
     };
 
-    Json(verbs).into_response()
+    verbs.extend(next_page(state, caller, items));
+
+    respond_with_content(state, caller, verbs)
 }
 
 #[axum::debug_handler]
-async fn call_events_now(State(state): State<AppState>) -> Response {
+async fn call_events_now(
+    State(state): State<AppState>,
+    uri: Uri,
+    Json(call): Json<CallDetails>,
+) -> Response {
     info!("Events now");
     counter!(crate::METRIC_REQUESTS_NAME, "endpoint" => "events_now").increment(1);
 
-    let now = Utc::now().into();
+    visit(&state, &call.call_sid, &uri);
+
+    let caller = Caller::new(&state, &call.call_sid);
+    let language = &caller.language;
+    let now = state.clock.now();
 
-    let negative = "There are no events in progress. Sad, I know. Or maybe it is a silly time and you should be asleep.";
-    let positive = "The following events are in progress.";
+    let negative = language.message("events-now-none");
+    let positive = language.message("events-now-intro");
 
     query_and_respond_with_a_list_of_events(
         &state,
+        &caller,
         |mut schedule| {
             let mutators = Mutators::new(vec![
                 Box::<SortedByStartTime>::default(),
@@ -180,33 +742,33 @@ async fn call_events_now(State(state): State<AppState>) -> Response {
         },
         negative,
         positive,
-        |event| {
-            let title = &event.title;
-            let speaker = &event.speaker;
-            let venue = &event.venue;
-            let started = crate::voice::format_duration(now - event.start);
-            let ending = crate::voice::format_duration(event.end - now);
-
-            crate::voice::speak_verb(&format!(
-                "Started {started} ago and ending in {ending} in {venue}: {title} by {speaker}."
-            ))
-        },
+        Readout::EventsNow,
+        now,
     )
     .await
 }
 
 #[axum::debug_handler]
-async fn call_events_starting_soon(State(state): State<AppState>) -> Response {
+async fn call_events_starting_soon(
+    State(state): State<AppState>,
+    uri: Uri,
+    Json(call): Json<CallDetails>,
+) -> Response {
     info!("Events starting soon");
-    counter!(crate::METRIC_REQUESTS_NAME, "endpoint" => "events_now_starting_soon").increment(1);
+    counter!(crate::METRIC_REQUESTS_NAME, "endpoint" => "events_starting_soon").increment(1);
+
+    visit(&state, &call.call_sid, &uri);
 
-    let now: DateTime<FixedOffset> = Utc::now().into();
+    let caller = Caller::new(&state, &call.call_sid);
+    let language = &caller.language;
+    let now: DateTime<FixedOffset> = state.clock.now();
 
-    let negative = "There are no events starting soon. Sad, I know. Or maybe it is a silly time and you should be asleep.";
-    let positive = "The following events may be of interest.";
+    let negative = language.message("events-starting-soon-none");
+    let positive = language.message("events-starting-soon-intro");
 
     query_and_respond_with_a_list_of_events(
         &state,
+        &caller,
         |mut schedule| {
             let range_start = now
                 + Duration::try_minutes(-5)
@@ -225,39 +787,33 @@ async fn call_events_starting_soon(State(state): State<AppState>) -> Response {
         },
         negative,
         positive,
-        |event| {
-            let title = &event.title;
-            let speaker = &event.speaker;
-            let venue = &event.venue;
-
-            if event.start < now {
-                let started = crate::voice::format_duration(now - event.start);
-                crate::voice::speak_verb(&format!(
-                    "Started {started} ago in {venue}: {title} by {speaker}."
-                ))
-            } else {
-                let starting = crate::voice::format_duration(event.start - now);
-                crate::voice::speak_verb(&format!(
-                    "Starting in {starting} in {venue}: {title} by {speaker}."
-                ))
-            }
-        },
+        Readout::EventsStartingSoon,
+        now,
     )
     .await
 }
 
 #[axum::debug_handler]
-async fn call_next_events_everywhere(State(state): State<AppState>) -> Response {
+async fn call_next_events_everywhere(
+    State(state): State<AppState>,
+    uri: Uri,
+    Json(call): Json<CallDetails>,
+) -> Response {
     info!("Next events at all venues");
-    counter!(crate::METRIC_REQUESTS_NAME, "endpoint" => "events_now").increment(1);
+    counter!(crate::METRIC_REQUESTS_NAME, "endpoint" => "next_events_everywhere").increment(1);
+
+    visit(&state, &call.call_sid, &uri);
 
-    let now = Utc::now().into();
+    let caller = Caller::new(&state, &call.call_sid);
+    let language = &caller.language;
+    let now = state.clock.now();
 
-    let negative = "There are no more events in the schedule. EMF 2024 is over. Everyone is sad, everyone apart from the spiders, and maybe the ducks.";
-    let positive = "Here are the next events.";
+    let negative = language.message("next-events-everywhere-none");
+    let positive = language.message("next-events-everywhere-intro");
 
     query_and_respond_with_a_list_of_events(
         &state,
+        &caller,
         |schedule| {
             let epg = schedule.now_and_next(now);
 
@@ -274,32 +830,88 @@ async fn call_next_events_everywhere(State(state): State<AppState>) -> Response
         },
         negative,
         positive,
-        |event| {
-            let title = &event.title;
-            let venue = &event.venue;
-            let start = crate::voice::format_timestamp_relative_to(event.start, now);
+        Readout::NextEventsEverywhere,
+        now,
+    )
+    .await
+}
+
+#[derive(Debug, Deserialize)]
+struct VenueQuery {
+    venue: String,
+}
+
+#[axum::debug_handler]
+async fn call_next_events_at_venue(
+    State(state): State<AppState>,
+    Query(query): Query<VenueQuery>,
+    uri: Uri,
+    Json(call): Json<CallDetails>,
+) -> Response {
+    info!("Next events at {}", query.venue);
+    counter!(crate::METRIC_REQUESTS_NAME, "endpoint" => "next_events_at_venue").increment(1);
 
-            crate::voice::speak_verb(&format!("Starting {start} at {venue}: {title}."))
+    visit(&state, &call.call_sid, &uri);
+
+    let caller = Caller::new(&state, &call.call_sid);
+    let language = &caller.language;
+    let now = state.clock.now();
+
+    let venue = query.venue;
+    let negative = language.format("next-events-at-venue-none", &[("venue", (&venue).into())]);
+    let positive = language.format("next-events-at-venue-intro", &[("venue", (&venue).into())]);
+
+    query_and_respond_with_a_list_of_events(
+        &state,
+        &caller,
+        |mut schedule| {
+            let mutators = Mutators::new(vec![
+                Box::new(EventAtVenue::new(venue.clone())),
+                Box::new(StartsAfter::new(now)),
+                Box::<SortedByStartTime>::default(),
+            ]);
+            schedule.mutate(&mutators);
+
+            schedule.events.truncate(3);
+            schedule.events
         },
+        negative,
+        positive,
+        Readout::NextEventsAtVenue,
+        now,
     )
     .await
 }
 
 #[axum::debug_handler]
-async fn call_upcoming_talks_summary(State(state): State<AppState>) -> Response {
+async fn call_upcoming_talks_summary(
+    State(state): State<AppState>,
+    uri: Uri,
+    Json(call): Json<CallDetails>,
+) -> Response {
     info!("Upcoming talks summary");
     counter!(crate::METRIC_REQUESTS_NAME, "endpoint" => "upcoming_talks_summary").increment(1);
 
-    let now = Utc::now().into();
+    visit(&state, &call.call_sid, &uri);
+
+    let caller = Caller::new(&state, &call.call_sid);
+    let language = &caller.language;
+    let now = state.clock.now();
 
     let hours = 3;
 
-    let negative = format!("There are no talks starting in the next {hours} hours. Maybe it is late and you should have a beer and enjoy some music. Sadly I can't join you, I am stuck in the telephone.");
-    let positive =
-        format!("Here are the talks you can look forward to over the next {hours} hours.");
+    let negative = language.format(
+        "upcoming-talks-none",
+        &[("hours", hours.to_string().into())],
+    );
+    let positive = language.format(
+        "upcoming-talks-intro",
+        &[("hours", hours.to_string().into())],
+    );
 
     query_and_respond_with_a_list_of_events(
         &state,
+        &caller,
         |mut schedule| {
             let until = now
                 + Duration::try_hours(hours)
@@ -314,33 +926,43 @@ async fn call_upcoming_talks_summary(State(state): State<AppState>) -> Response
             schedule.mutate(&mutators);
             schedule.events
         },
-        &negative,
-        &positive,
-        |event| {
-            let title = &event.title;
-            let venue = &event.venue;
-            let start = crate::voice::format_timestamp_relative_to(event.start, now);
-
-            crate::voice::speak_verb(&format!("Starting {start} at {venue}: {title}."))
-        },
+        negative,
+        positive,
+        Readout::UpcomingTalks,
+        now,
     )
     .await
 }
 
 #[axum::debug_handler]
-async fn call_upcoming_workshops_summary(State(state): State<AppState>) -> Response {
+async fn call_upcoming_workshops_summary(
+    State(state): State<AppState>,
+    uri: Uri,
+    Json(call): Json<CallDetails>,
+) -> Response {
     info!("Upcoming workshops summary");
     counter!(crate::METRIC_REQUESTS_NAME, "endpoint" => "upcoming_workshops_summary").increment(1);
 
-    let now = Utc::now().into();
+    visit(&state, &call.call_sid, &uri);
+
+    let caller = Caller::new(&state, &call.call_sid);
+    let language = &caller.language;
+    let now = state.clock.now();
 
     let hours = 3;
 
-    let negative = format!("There are no workshops starting in the next {hours} hours. Maybe it is late and you should have a beer and enjoy some music. Sadly I can't join you, I am stuck in the telephone.");
-    let positive = format!("Here are the workshops you can look forward to over the next {hours} hours. Well, assuming you won the appropriate ticket lottery.");
+    let negative = language.format(
+        "upcoming-workshops-none",
+        &[("hours", hours.to_string().into())],
+    );
+    let positive = language.format(
+        "upcoming-workshops-intro",
+        &[("hours", hours.to_string().into())],
+    );
 
     query_and_respond_with_a_list_of_events(
         &state,
+        &caller,
         |mut schedule| {
             let until = now
                 + Duration::try_hours(hours)
@@ -355,34 +977,44 @@ async fn call_upcoming_workshops_summary(State(state): State<AppState>) -> Respo
             schedule.mutate(&mutators);
             schedule.events
         },
-        &negative,
-        &positive,
-        |event| {
-            let title = &event.title;
-            let venue = &event.venue;
-            let start = crate::voice::format_timestamp_relative_to(event.start, now);
-
-            crate::voice::speak_verb(&format!("Starting {start} at {venue}: {title}."))
-        },
+        negative,
+        positive,
+        Readout::UpcomingWorkshops,
+        now,
     )
     .await
 }
 
 #[axum::debug_handler]
-async fn call_upcoming_performances_summary(State(state): State<AppState>) -> Response {
+async fn call_upcoming_performances_summary(
+    State(state): State<AppState>,
+    uri: Uri,
+    Json(call): Json<CallDetails>,
+) -> Response {
     info!("Upcoming performances summary");
     counter!(crate::METRIC_REQUESTS_NAME, "endpoint" => "upcoming_performances_summary")
         .increment(1);
 
-    let now = Utc::now().into();
+    visit(&state, &call.call_sid, &uri);
+
+    let caller = Caller::new(&state, &call.call_sid);
+    let language = &caller.language;
+    let now = state.clock.now();
 
     let hours = 3;
 
-    let negative = format!("There are no performances starting in the next {hours} hours. Maybe you could find an interesting talk to pass the time?");
-    let positive = format!("Here are the performances taking place over the next {hours} hours.");
+    let negative = language.format(
+        "upcoming-performances-none",
+        &[("hours", hours.to_string().into())],
+    );
+    let positive = language.format(
+        "upcoming-performances-intro",
+        &[("hours", hours.to_string().into())],
+    );
 
     query_and_respond_with_a_list_of_events(
         &state,
+        &caller,
         |mut schedule| {
             let until = now
                 + Duration::try_hours(hours)
@@ -397,15 +1029,244 @@ async fn call_upcoming_performances_summary(State(state): State<AppState>) -> Re
             schedule.mutate(&mutators);
             schedule.events
         },
-        &negative,
-        &positive,
-        |event| {
-            let title = &event.title;
-            let venue = &event.venue;
-            let start = crate::voice::format_timestamp_relative_to(event.start, now);
-
-            crate::voice::speak_verb(&format!("Starting {start} at {venue}: {title}."))
-        },
+        negative,
+        positive,
+        Readout::UpcomingPerformances,
+        now,
     )
     .await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cache::{ScheduleCache, ScheduleSource},
+        fixtures::{self, verbs},
+    };
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+
+    async fn call(state: &AppState, path: &str, body: Value) -> Value {
+        let app = build_router().with_state(state.clone());
+        let (status, response) = fixtures::post(app, path, body.to_string()).await;
+        assert_eq!(status, StatusCode::OK, "{path}");
+        response
+    }
+
+    /// What is said by a gather, or the first verb of a response.
+    fn said(response: &Value) -> &str {
+        let verb = &response[0];
+        verb["say"]["text"]
+            .as_str()
+            .or(verb["text"].as_str())
+            .unwrap()
+    }
+
+    fn no_input() -> Value {
+        json!({ "call_sid": "abc", "reason": "timeout" })
+    }
+
+    fn digits(digits: &str) -> Value {
+        json!({ "call_sid": "abc", "reason": "dtmfDetected", "digits": digits })
+    }
+
+    fn speech(transcript: &str) -> Value {
+        json!({
+            "call_sid": "abc",
+            "reason": "speechDetected",
+            "speech": { "alternatives": [{ "transcript": transcript, "confidence": 0.9 }] },
+        })
+    }
+
+    /// Asks for events on now from the speech query, giving the content that was read out.
+    async fn content(state: &AppState) -> Value {
+        call(state, "/call/query", json!({ "call_sid": "abc" })).await;
+        call(state, "/call/events_now", json!({ "call_sid": "abc" })).await
+    }
+
+    #[tokio::test]
+    async fn reprompts_then_hangs_up() {
+        let state = fixtures::state();
+
+        let menu = call(&state, "/call/menu", json!({ "call_sid": "abc" })).await;
+        assert_eq!(verbs(&menu), ["gather"]);
+        assert!(said(&menu).starts_with("Press 1 for"), "{}", said(&menu));
+
+        for reprompt in ["Sorry, I did not hear", "I still did not hear"] {
+            let response = call(&state, "/call/menu_selection", no_input()).await;
+            assert_eq!(verbs(&response), ["redirect"]);
+            assert_eq!(response[0]["actionHook"], "/call/menu");
+
+            let menu = call(&state, "/call/menu", json!({ "call_sid": "abc" })).await;
+            assert!(said(&menu).starts_with(reprompt), "{}", said(&menu));
+        }
+
+        let response = call(&state, "/call/menu_selection", no_input()).await;
+        assert_eq!(verbs(&response), ["say", "hangup"]);
+        assert_eq!(said(&response), "No response was received. Goodbye.");
+    }
+
+    #[tokio::test]
+    async fn input_resets_reprompts() {
+        let state = fixtures::state();
+
+        for _ in 0..state.menu_max_reprompts {
+            let response = call(&state, "/call/menu_selection", no_input()).await;
+            assert_eq!(verbs(&response), ["redirect"]);
+        }
+
+        let response = call(
+            &state,
+            "/call/menu_selection",
+            json!({ "call_sid": "abc", "reason": "dtmfDetected", "digits": "1" }),
+        )
+        .await;
+        assert_eq!(response[0]["actionHook"], "/call/events_now");
+
+        let response = call(&state, "/call/menu_selection", no_input()).await;
+        assert_eq!(verbs(&response), ["redirect"]);
+        assert_eq!(response[0]["actionHook"], "/call/menu");
+    }
+
+    #[tokio::test]
+    async fn reprompts_return_to_submenu() {
+        let state = fixtures::state();
+
+        let response = call(&state, "/call/menu_selection?path=2", no_input()).await;
+        assert_eq!(response[0]["actionHook"], "/call/menu?path=2");
+    }
+
+    #[tokio::test]
+    async fn navigation_repeats() {
+        for input in [digits("*"), speech("say that again")] {
+            let state = fixtures::state();
+            let content = content(&state).await;
+            assert_eq!(verbs(&content).last(), Some(&"gather"));
+
+            let response = call(&state, "/call/navigation", input).await;
+            assert_eq!(response, content);
+        }
+    }
+
+    #[tokio::test]
+    async fn navigation_goes_back() {
+        for input in [digits("#"), speech("go back")] {
+            let state = fixtures::state();
+            content(&state).await;
+
+            let response = call(&state, "/call/navigation", input.clone()).await;
+            assert_eq!(verbs(&response), ["redirect"]);
+            assert_eq!(response[0]["actionHook"], "/call/query");
+
+            // With nowhere left to go back to, the main menu
+            let response = call(&state, "/call/navigation", input).await;
+            assert_eq!(response[0]["actionHook"], "/call/menu");
+        }
+    }
+
+    #[tokio::test]
+    async fn navigation_help() {
+        let state = fixtures::state();
+        content(&state).await;
+
+        let response = call(&state, "/call/navigation", digits("0")).await;
+        assert_eq!(verbs(&response), ["say", "gather"]);
+        assert!(said(&response).starts_with("After each message"));
+    }
+
+    #[tokio::test]
+    async fn navigation_rejects_invalid_input() {
+        let state = fixtures::state();
+        content(&state).await;
+
+        for input in ["7", "9"] {
+            let response = call(&state, "/call/navigation", digits(input)).await;
+            assert_eq!(verbs(&response), ["say", "gather"]);
+            assert_eq!(said(&response), format!("{input} is not a valid option."));
+        }
+
+        let response = call(&state, "/call/navigation", speech("background music")).await;
+        assert_eq!(verbs(&response), ["say", "gather"]);
+        assert_eq!(said(&response), "Sorry, I did not understand that.");
+    }
+
+    #[tokio::test]
+    async fn navigation_hangs_up_without_input() {
+        let state = fixtures::state();
+        content(&state).await;
+
+        let response = call(&state, "/call/navigation", no_input()).await;
+        assert_eq!(verbs(&response), ["say", "hangup"]);
+    }
+
+    #[tokio::test]
+    async fn paging() {
+        let events = (1..=10)
+            .map(|n| {
+                fixtures::event(
+                    n,
+                    &format!("Event {n}"),
+                    &format!("Venue {n}"),
+                    "2024-06-01T11:30:00+01:00",
+                    "2024-06-01T12:30:00+01:00",
+                )
+            })
+            .collect();
+        let state = AppState {
+            schedule: Arc::new(ScheduleCache::new(
+                ScheduleSource::file(fixtures::schedule_file(&Schedule { events })),
+                None,
+            )),
+            ..fixtures::state()
+        };
+        let text = |response: &Value| {
+            response
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter_map(|verb| verb["text"].as_str().or(verb["say"]["text"].as_str()))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        let page = call(&state, "/call/events_now", json!({ "call_sid": "abc" })).await;
+        let page = text(&page);
+        assert!(
+            page.contains("Event 4") && !page.contains("Event 5"),
+            "{page}"
+        );
+        assert!(
+            page.contains("There are 6 more events. Press 1 to continue"),
+            "{page}"
+        );
+        let page = call(&state, "/call/navigation", digits("1")).await;
+        let page = text(&page);
+        assert!(
+            page.contains("Event 5") && page.contains("Event 8"),
+            "{page}"
+        );
+        assert!(
+            !page.contains("Event 4") && !page.contains("Event 9"),
+            "{page}"
+        );
+        assert!(
+            page.contains("There are 2 more events. Press 1 to continue"),
+            "{page}"
+        );
+
+        let page = call(&state, "/call/navigation", digits("1")).await;
+        let page = text(&page);
+        assert!(
+            page.contains("Event 9") && page.contains("Event 10"),
+            "{page}"
+        );
+        assert!(
+            !page.contains("more") && !page.contains("Press 1"),
+            "{page}"
+        );
+
+        let response = call(&state, "/call/navigation", digits("1")).await;
+        assert_eq!(said(&response), "1 is not a valid option.");
+    }
+}
@@ -1,13 +1,45 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::HashMap;
 
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "snake_case", tag = "verb")]
-#[allow(dead_code)]
+#[allow(clippy::large_enum_variant)]
+#[cfg_attr(not(test), allow(dead_code))]
 pub(crate) enum Verb {
     Redirect(Redirect),
     Pause(Pause),
     Say(Say),
+    Play(Play),
     Gather(Gather),
+    Hangup(Hangup),
+    Dial(Dial),
+    Message(Message),
+    Config(Config),
+    Dtmf(Dtmf),
+    Tag(Tag),
+    Conference(Conference),
+    Enqueue(Enqueue),
+    Dequeue(Dequeue),
+    Leave(Leave),
+    #[serde(rename = "sip:refer")]
+    SipRefer(SipRefer),
+}
+
+/// Number of times a prompt is played, see https://www.jambonz.org/docs/webhooks/say/
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(not(test), allow(dead_code))]
+pub(crate) enum Loop {
+    Count(u32),
+    Forever,
+}
+
+impl Serialize for Loop {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Count(count) => serializer.serialize_u32(*count),
+            Self::Forever => serializer.serialize_str("forever"),
+        }
+    }
 }
 
 /// See https://www.jambonz.org/docs/webhooks/redirect/
@@ -23,17 +55,54 @@ pub(crate) struct Pause {
     pub length: u64,
 }
 
+/// See https://www.jambonz.org/docs/webhooks/hangup/
+#[derive(Debug, Clone, Default, Serialize)]
+pub(crate) struct Hangup {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub headers: Option<HashMap<String, String>>,
+}
+
 /// See https://www.jambonz.org/docs/webhooks/say/
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub(crate) struct Say {
     pub text: String,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub synthesizer: Option<SaySynthesizer>,
+
+    #[serde(rename = "loop", skip_serializing_if = "Option::is_none")]
+    pub loop_: Option<Loop>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub early_media: Option<bool>,
+}
+
+/// See https://www.jambonz.org/docs/webhooks/play/
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Play {
+    pub url: String,
+
+    #[serde(rename = "loop", skip_serializing_if = "Option::is_none")]
+    pub loop_: Option<Loop>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub early_media: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seek_offset: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action_hook: Option<String>,
 }
 
 /// See https://www.jambonz.org/docs/webhooks/say/
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub(crate) struct SaySynthesizer {
     pub vendor: String,
 
@@ -43,10 +112,25 @@ pub(crate) struct SaySynthesizer {
     pub gender: Option<String>,
 
     pub voice: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub engine: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub options: Option<serde_json::Value>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fallback_vendor: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fallback_language: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fallback_voice: Option<String>,
 }
 
 /// See https://www.jambonz.org/docs/webhooks/gather/
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Default, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct Gather {
     pub action_hook: String,
@@ -55,11 +139,42 @@ pub(crate) struct Gather {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub num_digits: Option<usize>,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_digits: Option<usize>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_digits: Option<usize>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finish_on_key: Option<String>,
+
+    /// Seconds to wait for input after the prompt has finished.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+
+    /// Seconds to wait between keypresses.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inter_digit_timeout: Option<u64>,
+
+    /// Whether speech from the caller interrupts the prompt.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bargein: Option<bool>,
+
+    /// Whether a keypress from the caller interrupts the prompt.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dtmf_bargein: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partial_result_hook: Option<String>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub recognizer: Option<GatherRecognizer>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub say: Option<Say>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub play: Option<Play>,
 }
 
 /// See https://www.jambonz.org/docs/webhooks/gather/
@@ -67,11 +182,11 @@ pub(crate) struct Gather {
 #[serde(rename_all = "camelCase")]
 pub(crate) enum GatherInputs {
     Digits,
-    // Speech,
+    Speech,
 }
 
 /// See https://www.jambonz.org/docs/webhooks/gather/
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Default, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct GatherRecognizer {
     pub vendor: String,
@@ -83,10 +198,275 @@ pub(crate) struct GatherRecognizer {
     pub hints_boost: i32,
 }
 
+/// See https://www.jambonz.org/docs/webhooks/dial/
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Dial {
+    pub target: Vec<DialTarget>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action_hook: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub answer_on_bridge: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub caller_id: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dial_music: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub headers: Option<HashMap<String, String>>,
+
+    /// Maximum length of the bridged call in seconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_limit: Option<u64>,
+
+    /// Seconds to wait for the target to answer.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+}
+
+/// See https://www.jambonz.org/docs/webhooks/dial/
+#[derive(Debug, Clone, Serialize)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "type"
+)]
+#[cfg_attr(not(test), allow(dead_code))]
+pub(crate) enum DialTarget {
+    Phone {
+        number: String,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        trunk: Option<String>,
+    },
+    Sip {
+        sip_uri: String,
+    },
+    User {
+        name: String,
+    },
+    Teams {
+        number: String,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        tenant: Option<String>,
+    },
+}
+
+/// See https://www.jambonz.org/docs/webhooks/message/
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Message {
+    pub to: String,
+    pub from: String,
+    pub text: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub carrier: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action_hook: Option<String>,
+}
+
+/// See https://www.jambonz.org/docs/webhooks/config/
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Config {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub synthesizer: Option<SaySynthesizer>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recognizer: Option<GatherRecognizer>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub barge_in: Option<ConfigBargeIn>,
+
+    /// Session-level settings to reset back to their defaults, e.g. "synthesizer".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reset: Option<Vec<String>>,
+}
+
+/// See https://www.jambonz.org/docs/webhooks/config/
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ConfigBargeIn {
+    pub enable: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action_hook: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input: Option<Vec<GatherInputs>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_bargein_word_count: Option<usize>,
+}
+
+/// See https://www.jambonz.org/docs/webhooks/dtmf/
+#[derive(Debug, Clone, Default, Serialize)]
+pub(crate) struct Dtmf {
+    pub dtmf: String,
+
+    /// Duration of each tone in milliseconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration: Option<u64>,
+}
+
+/// See https://www.jambonz.org/docs/webhooks/tag/
+#[derive(Debug, Clone, Default, Serialize)]
+pub(crate) struct Tag {
+    pub data: HashMap<String, String>,
+}
+
+/// See https://www.jambonz.org/docs/webhooks/conference/
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Conference {
+    pub name: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub beep: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_conference_on_enter: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_conference_on_exit: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub join_muted: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_participants: Option<usize>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action_hook: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wait_hook: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_hook: Option<String>,
+}
+
+/// See https://www.jambonz.org/docs/webhooks/enqueue/
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Enqueue {
+    pub name: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action_hook: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wait_hook: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<u32>,
+}
+
+/// See https://www.jambonz.org/docs/webhooks/dequeue/
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Dequeue {
+    pub name: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action_hook: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub beep: Option<bool>,
+
+    /// Seconds to wait for a call to appear in the queue.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+}
+
+/// See https://www.jambonz.org/docs/webhooks/leave/
+#[derive(Debug, Clone, Default, Serialize)]
+pub(crate) struct Leave {}
+
+/// See https://www.jambonz.org/docs/webhooks/sip-refer/
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SipRefer {
+    pub refer_to: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub referred_by: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub headers: Option<HashMap<String, String>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action_hook: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event_hook: Option<String>,
+}
+
+/// The details jambonz sends with every webhook request, of which only the call SID is of interest.
+///
+/// See https://www.jambonz.org/docs/webhooks/overview/
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct CallDetails {
+    pub call_sid: String,
+}
+
 /// See https://www.jambonz.org/docs/webhooks/gather/
 #[derive(Debug, Deserialize)]
 pub(crate) struct GatherResponse {
+    pub call_sid: String,
+
+    pub reason: Option<GatherReason>,
+
     pub digits: Option<String>,
+
+    pub speech: Option<GatherSpeech>,
+}
+
+impl GatherResponse {
+    /// The most likely transcript of what the caller said, along with the recognizer's confidence
+    /// in it (if the recognizer provided one).
+    pub(crate) fn transcript(&self) -> Option<(&str, Option<f64>)> {
+        self.speech
+            .as_ref()
+            .and_then(|speech| speech.alternatives.first())
+            .map(|alternative| (alternative.transcript.as_str(), alternative.confidence))
+    }
+}
+
+/// See https://www.jambonz.org/docs/webhooks/gather/
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum GatherReason {
+    SpeechDetected,
+    DtmfDetected,
+    Timeout,
+    #[serde(rename = "stt-error")]
+    SttError,
+    #[serde(rename = "stt-low-confidence")]
+    SttLowConfidence,
+    #[serde(other)]
+    Other,
+}
+
+/// See https://www.jambonz.org/docs/webhooks/gather/
+#[derive(Debug, Deserialize)]
+pub(crate) struct GatherSpeech {
+    pub alternatives: Vec<GatherSpeechAlternative>,
+}
+
+/// See https://www.jambonz.org/docs/webhooks/gather/
+#[derive(Debug, Deserialize)]
+pub(crate) struct GatherSpeechAlternative {
+    pub transcript: String,
+
+    pub confidence: Option<f64>,
 }
 
 /// See https://www.jambonz.org/docs/webhooks/overview/
@@ -95,16 +475,15 @@ pub(crate) struct CallStatusDetails {
     #[allow(unused)]
     call_id: String,
 
-    #[allow(unused)]
-    call_sid: String,
+    pub call_sid: String,
 
     pub call_status: CallStatus,
 
     #[allow(unused)]
     call_termination_by: Option<String>,
 
-    #[allow(unused)]
-    duration: Option<i64>,
+    /// Length of the call in seconds, once it has ended.
+    pub duration: Option<i64>,
 
     pub from: String,
 }
@@ -122,3 +501,283 @@ pub(crate) enum CallStatus {
     Busy,
     NoAnswer,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn to_json(verb: Verb) -> Value {
+        serde_json::to_value(verb).unwrap()
+    }
+
+    #[test]
+    fn say_is_tagged_and_omits_unset_options() {
+        let verb = Verb::Say(Say {
+            text: "Hello".to_string(),
+            ..Default::default()
+        });
+
+        assert_eq!(to_json(verb), json!({"verb": "say", "text": "Hello"}));
+    }
+
+    #[test]
+    fn say_with_synthesizer_and_loop() {
+        let verb = Verb::Say(Say {
+            text: "Hello".to_string(),
+            synthesizer: Some(SaySynthesizer {
+                vendor: "aws".to_string(),
+                language: "en-GB".to_string(),
+                voice: "Amy".to_string(),
+                fallback_vendor: Some("google".to_string()),
+                fallback_voice: Some("en-GB-Standard-A".to_string()),
+                ..Default::default()
+            }),
+            loop_: Some(Loop::Count(2)),
+            early_media: Some(true),
+        });
+
+        assert_eq!(
+            to_json(verb),
+            json!({
+                "verb": "say",
+                "text": "Hello",
+                "synthesizer": {
+                    "vendor": "aws",
+                    "language": "en-GB",
+                    "voice": "Amy",
+                    "fallbackVendor": "google",
+                    "fallbackVoice": "en-GB-Standard-A",
+                },
+                "loop": 2,
+                "earlyMedia": true,
+            })
+        );
+    }
+
+    #[test]
+    fn play_forever() {
+        let verb = Verb::Play(Play {
+            url: "https://example.org/hold.mp3".to_string(),
+            loop_: Some(Loop::Forever),
+            timeout_secs: Some(30),
+            ..Default::default()
+        });
+
+        assert_eq!(
+            to_json(verb),
+            json!({
+                "verb": "play",
+                "url": "https://example.org/hold.mp3",
+                "loop": "forever",
+                "timeoutSecs": 30,
+            })
+        );
+    }
+
+    #[test]
+    fn redirect_pause_and_hangup() {
+        assert_eq!(
+            to_json(Verb::Redirect(Redirect {
+                action_hook: "/call/menu".to_string(),
+            })),
+            json!({"verb": "redirect", "actionHook": "/call/menu"})
+        );
+        assert_eq!(
+            to_json(Verb::Pause(Pause { length: 3 })),
+            json!({"verb": "pause", "length": 3})
+        );
+        assert_eq!(
+            to_json(Verb::Hangup(Hangup::default())),
+            json!({"verb": "hangup"})
+        );
+    }
+
+    #[test]
+    fn gather_with_nested_say_and_recognizer() {
+        let verb = Verb::Gather(Gather {
+            action_hook: "/call/menu_selection".to_string(),
+            input: vec![GatherInputs::Digits, GatherInputs::Speech],
+            num_digits: Some(1),
+            dtmf_bargein: Some(true),
+            recognizer: Some(GatherRecognizer {
+                vendor: "google".to_string(),
+                language: "en-GB".to_string(),
+                hints: vec!["Stage A".to_string()],
+                hints_boost: 10,
+            }),
+            say: Some(Say {
+                text: "Press 1".to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+
+        assert_eq!(
+            to_json(verb),
+            json!({
+                "verb": "gather",
+                "actionHook": "/call/menu_selection",
+                "input": ["digits", "speech"],
+                "numDigits": 1,
+                "dtmfBargein": true,
+                "recognizer": {
+                    "vendor": "google",
+                    "language": "en-GB",
+                    "hints": ["Stage A"],
+                    "hintsBoost": 10,
+                },
+                "say": {"text": "Press 1"},
+            })
+        );
+    }
+
+    #[test]
+    fn dial_targets() {
+        let verb = Verb::Dial(Dial {
+            target: vec![
+                DialTarget::Phone {
+                    number: "+441234567890".to_string(),
+                    trunk: None,
+                },
+                DialTarget::Phone {
+                    number: "+441234567890".to_string(),
+                    trunk: Some("carrier".to_string()),
+                },
+                DialTarget::Sip {
+                    sip_uri: "sip:info@example.org".to_string(),
+                },
+                DialTarget::User {
+                    name: "info@example.org".to_string(),
+                },
+                DialTarget::Teams {
+                    number: "+441234567890".to_string(),
+                    tenant: Some("example.onmicrosoft.com".to_string()),
+                },
+            ],
+            answer_on_bridge: Some(true),
+            time_limit: Some(600),
+            ..Default::default()
+        });
+
+        assert_eq!(
+            to_json(verb),
+            json!({
+                "verb": "dial",
+                "target": [
+                    {"type": "phone", "number": "+441234567890"},
+                    {"type": "phone", "number": "+441234567890", "trunk": "carrier"},
+                    {"type": "sip", "sipUri": "sip:info@example.org"},
+                    {"type": "user", "name": "info@example.org"},
+                    {
+                        "type": "teams",
+                        "number": "+441234567890",
+                        "tenant": "example.onmicrosoft.com",
+                    },
+                ],
+                "answerOnBridge": true,
+                "timeLimit": 600,
+            })
+        );
+    }
+
+    #[test]
+    fn sip_refer() {
+        let verb = Verb::SipRefer(SipRefer {
+            refer_to: "sip:info@example.org".to_string(),
+            action_hook: Some("/call/refer".to_string()),
+            ..Default::default()
+        });
+
+        assert_eq!(
+            to_json(verb),
+            json!({
+                "verb": "sip:refer",
+                "referTo": "sip:info@example.org",
+                "actionHook": "/call/refer",
+            })
+        );
+    }
+
+    #[test]
+    fn message_and_config() {
+        assert_eq!(
+            to_json(Verb::Message(Message {
+                to: "+441234567890".to_string(),
+                from: "+440987654321".to_string(),
+                text: "Hello".to_string(),
+                ..Default::default()
+            })),
+            json!({
+                "verb": "message",
+                "to": "+441234567890",
+                "from": "+440987654321",
+                "text": "Hello",
+            })
+        );
+
+        assert_eq!(
+            to_json(Verb::Config(Config {
+                barge_in: Some(ConfigBargeIn {
+                    enable: true,
+                    input: Some(vec![GatherInputs::Digits]),
+                    min_bargein_word_count: Some(2),
+                    ..Default::default()
+                }),
+                reset: Some(vec!["synthesizer".to_string()]),
+                ..Default::default()
+            })),
+            json!({
+                "verb": "config",
+                "bargeIn": {"enable": true, "input": ["digits"], "minBargeinWordCount": 2},
+                "reset": ["synthesizer"],
+            })
+        );
+    }
+
+    #[test]
+    fn dtmf_and_tag() {
+        assert_eq!(
+            to_json(Verb::Dtmf(Dtmf {
+                dtmf: "0276".to_string(),
+                duration: Some(250),
+            })),
+            json!({"verb": "dtmf", "dtmf": "0276", "duration": 250})
+        );
+        assert_eq!(
+            to_json(Verb::Tag(Tag {
+                data: [("menu".to_string(), "main".to_string())].into(),
+            })),
+            json!({"verb": "tag", "data": {"menu": "main"}})
+        );
+    }
+
+    #[test]
+    fn queues_and_conferences() {
+        assert_eq!(
+            to_json(Verb::Conference(Conference {
+                name: "info-desk".to_string(),
+                start_conference_on_enter: Some(true),
+                ..Default::default()
+            })),
+            json!({"verb": "conference", "name": "info-desk", "startConferenceOnEnter": true})
+        );
+        assert_eq!(
+            to_json(Verb::Enqueue(Enqueue {
+                name: "info-desk".to_string(),
+                wait_hook: Some("/call/wait".to_string()),
+                ..Default::default()
+            })),
+            json!({"verb": "enqueue", "name": "info-desk", "waitHook": "/call/wait"})
+        );
+        assert_eq!(
+            to_json(Verb::Dequeue(Dequeue {
+                name: "info-desk".to_string(),
+                timeout: Some(10),
+                ..Default::default()
+            })),
+            json!({"verb": "dequeue", "name": "info-desk", "timeout": 10})
+        );
+        assert_eq!(to_json(Verb::Leave(Leave {})), json!({"verb": "leave"}));
+    }
+}
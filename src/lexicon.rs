@@ -0,0 +1,344 @@
+use crate::watch::WatchedFile;
+use emfcamp_schedule_api::schedule::Schedule;
+use serde::Deserialize;
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tracing::{info, warn};
+
+/// How a word that synthesizers commonly get wrong should be spoken.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum Pronunciation {
+    /// Replace the word with something that is read correctly.
+    Substitute(String),
+
+    /// Use an IPA pronunciation, or the fallback text when SSML is not available.
+    Phoneme { ipa: String, fallback: String },
+}
+
+/// Words and how they should be pronounced.
+///
+/// Words are matched exactly first, then by their lowercase form, so an entry written in lowercase
+/// applies regardless of how the word is capitalised.
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct Lexicon {
+    words: HashMap<String, Pronunciation>,
+}
+
+impl Lexicon {
+    /// Words that are always known when speaking English, unless overridden by the lexicon file.
+    pub(crate) fn builtin() -> Self {
+        let words = [
+            ("EMF", Pronunciation::Substitute("E M F".to_string())),
+            (
+                "CfP",
+                Pronunciation::Substitute("call for participation".to_string()),
+            ),
+            (
+                "DECT",
+                Pronunciation::Phoneme {
+                    ipa: "dɛkt".to_string(),
+                    fallback: "deckt".to_string(),
+                },
+            ),
+        ];
+
+        Self {
+            words: words
+                .into_iter()
+                .map(|(word, pronunciation)| (word.to_string(), pronunciation))
+                .collect(),
+        }
+    }
+
+    /// Parses a TOML lexicon file.
+    fn parse(contents: &str) -> anyhow::Result<Self> {
+        Ok(toml::from_str(contents)?)
+    }
+
+    pub(crate) fn get(&self, word: &str) -> Option<&Pronunciation> {
+        self.words
+            .get(word)
+            .or_else(|| self.words.get(&word.to_lowercase()))
+    }
+
+    /// Gets every distinct word in the speakers, venues and titles of the schedule that is not in
+    /// the lexicon.
+    pub(crate) fn unknown_words<'a>(&self, schedule: &'a Schedule) -> BTreeSet<&'a str> {
+        schedule
+            .events
+            .iter()
+            .flat_map(|event| [&event.speaker, &event.venue, &event.title])
+            .flat_map(|text| tokens(text))
+            .filter(|(token, is_word)| {
+                *is_word && !token.chars().all(|c| c.is_numeric()) && self.get(token).is_none()
+            })
+            .map(|(token, _)| token)
+            .collect()
+    }
+}
+
+/// Splits text into words and the single non-word characters between them, flagging which is
+/// which.
+pub(crate) fn tokens(text: &str) -> impl Iterator<Item = (&str, bool)> {
+    let mut rest = text;
+
+    std::iter::from_fn(move || {
+        let c = rest.chars().next()?;
+
+        let word_end = rest
+            .find(|c: char| !c.is_alphanumeric())
+            .unwrap_or(rest.len());
+
+        let (token, remaining) = if word_end == 0 {
+            rest.split_at(c.len_utf8())
+        } else {
+            rest.split_at(word_end)
+        };
+        rest = remaining;
+
+        Some((token, word_end != 0))
+    })
+}
+
+/// The lexicon for English, which includes the builtin words, and the lexicon for every other
+/// language.
+struct Lexicons {
+    english: Arc<Lexicon>,
+    other: Arc<Lexicon>,
+}
+
+impl Lexicons {
+    fn new(file: Lexicon) -> Self {
+        let mut english = Lexicon::builtin();
+        english.words.extend(file.words.clone());
+
+        Self {
+            english: Arc::new(english),
+            other: Arc::new(file),
+        }
+    }
+}
+
+/// The current lexicons, optionally loaded from a file that is reloaded whenever it changes.
+///
+/// Words from the file, which are mostly names, are used in every language. The builtin words are
+/// English, so are only used when speaking English.
+pub(crate) struct LexiconStore {
+    file: Option<WatchedFile>,
+    lexicons: RwLock<Lexicons>,
+}
+
+impl LexiconStore {
+    pub(crate) fn builtin() -> Self {
+        Self {
+            file: None,
+            lexicons: RwLock::new(Lexicons::new(Lexicon::default())),
+        }
+    }
+
+    /// Loads the lexicon from a file, failing if the file cannot be loaded.
+    pub(crate) async fn file(path: PathBuf) -> anyhow::Result<Self> {
+        let store = Self {
+            file: Some(WatchedFile::new(path)),
+            ..Self::builtin()
+        };
+        store.reload().await?;
+
+        Ok(store)
+    }
+
+    /// Gets the lexicon for a language, given as a code such as "en-GB".
+    pub(crate) fn get(&self, language: &str) -> Arc<Lexicon> {
+        let lexicons = self
+            .lexicons
+            .read()
+            .expect("lexicon lock should not be poisoned");
+
+        let english = language
+            .split('-')
+            .next()
+            .is_some_and(|code| code.eq_ignore_ascii_case("en"));
+        if english {
+            lexicons.english.clone()
+        } else {
+            lexicons.other.clone()
+        }
+    }
+
+    /// Checks the lexicon file for changes on a fixed interval for as long as the application
+    /// runs, keeping the previous lexicon if the file cannot be loaded.
+    pub(crate) fn spawn_reload(self: Arc<Self>, interval: Duration) {
+        if self.file.is_none() {
+            return;
+        }
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+
+            loop {
+                interval.tick().await;
+
+                if let Err(e) = self.reload().await {
+                    warn!("Failed to reload lexicon: {e}");
+                }
+            }
+        });
+    }
+
+    async fn reload(&self) -> anyhow::Result<()> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+
+        let lexicon = file
+            .load_if_changed(|contents| Lexicon::parse(&String::from_utf8(contents)?))
+            .await?;
+        let Some(lexicon) = lexicon else {
+            return Ok(());
+        };
+
+        info!(
+            "Loaded {} words from lexicon {}",
+            lexicon.words.len(),
+            file.path().display()
+        );
+        *self
+            .lexicons
+            .write()
+            .expect("lexicon lock should not be poisoned") = Lexicons::new(lexicon);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures, watch::tests::set_modified};
+
+    fn substitute<'a>(lexicon: &'a Lexicon, word: &str) -> Option<&'a str> {
+        match lexicon.get(word)? {
+            Pronunciation::Substitute(text) => Some(text),
+            Pronunciation::Phoneme { .. } => None,
+        }
+    }
+
+    #[test]
+    fn tokens_split_words() {
+        assert_eq!(
+            tokens("Rock & roll, DECT-phones!").collect::<Vec<_>>(),
+            [
+                ("Rock", true),
+                (" ", false),
+                ("&", false),
+                (" ", false),
+                ("roll", true),
+                (",", false),
+                (" ", false),
+                ("DECT", true),
+                ("-", false),
+                ("phones", true),
+                ("!", false),
+            ]
+        );
+        assert_eq!(
+            tokens("Zürich 2024").collect::<Vec<_>>(),
+            [("Zürich", true), (" ", false), ("2024", true)]
+        );
+        assert_eq!(tokens("").count(), 0);
+    }
+
+    #[test]
+    fn words_match_exactly_then_in_lowercase() {
+        let lexicon = Lexicon::parse(
+            r#"
+            [words]
+            nixon = "Nicks on"
+            Tor = "T O R"
+            "#,
+        )
+        .unwrap();
+
+        for word in ["nixon", "Nixon", "NIXON"] {
+            assert_eq!(substitute(&lexicon, word), Some("Nicks on"), "{word}");
+        }
+        assert_eq!(substitute(&lexicon, "Tor"), Some("T O R"));
+        assert!(lexicon.get("tor").is_none());
+        assert!(lexicon.get("EMF").is_none());
+    }
+
+    #[test]
+    fn unknown_words() {
+        let lexicon = Lexicon::builtin();
+        let schedule = Schedule {
+            events: vec![
+                fixtures::event(1, "EMF DECT 101", "Stage A", fixtures::NOW, fixtures::NOW),
+                fixtures::event(2, "Welcome to EMF", "Stage A", fixtures::NOW, fixtures::NOW),
+            ],
+        };
+
+        assert_eq!(
+            lexicon
+                .unknown_words(&schedule)
+                .into_iter()
+                .collect::<Vec<_>>(),
+            ["A", "Someone", "Stage", "Welcome", "to"]
+        );
+    }
+
+    #[test]
+    fn builtin_words_are_english() {
+        let store = LexiconStore::builtin();
+
+        assert_eq!(substitute(&store.get("en-GB"), "EMF"), Some("E M F"));
+        assert!(store.get("en").get("EMF").is_some());
+        assert!(store.get("de-DE").get("EMF").is_none());
+    }
+
+    #[tokio::test]
+    async fn file_is_reloaded_when_modified() {
+        let path = fixtures::temp_path("lexicon.toml");
+        std::fs::write(
+            &path,
+            "[words]\nNixon = \"Nicks on\"\nEMF = \"Electromagnetic Field\"\n",
+        )
+        .unwrap();
+        set_modified(&path, 1000);
+        let store = LexiconStore::file(path.clone()).await.unwrap();
+
+        // File words apply to every language, and take the place of builtin words
+        for language in ["en-GB", "de-DE"] {
+            let lexicon = store.get(language);
+            assert!(lexicon.get("Nixon").is_some(), "{language}");
+            assert_eq!(
+                substitute(&lexicon, "EMF"),
+                Some("Electromagnetic Field"),
+                "{language}"
+            );
+        }
+        assert!(store.get("en-GB").get("CfP").is_some());
+        assert!(store.get("de-DE").get("CfP").is_none());
+
+        std::fs::write(&path, "[words]\nTor = \"T O R\"\n").unwrap();
+        set_modified(&path, 1000);
+        store.reload().await.unwrap();
+        assert!(store.get("en-GB").get("Nixon").is_some());
+
+        set_modified(&path, 2000);
+        store.reload().await.unwrap();
+        assert!(store.get("en-GB").get("Nixon").is_none());
+        assert!(store.get("en-GB").get("Tor").is_some());
+        assert!(store.get("en-GB").get("EMF").is_some());
+
+        // A broken file keeps the previous lexicon
+        std::fs::write(&path, "[words").unwrap();
+        set_modified(&path, 3000);
+        assert!(store.reload().await.is_err());
+        assert!(store.get("en-GB").get("Tor").is_some());
+    }
+}
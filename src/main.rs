@@ -1,17 +1,47 @@
+mod cache;
+mod callers;
+mod catalog;
+mod clock;
+mod fallback;
+#[cfg(test)]
+mod fixtures;
 mod handlers;
 mod jambonz;
+mod lexicon;
+mod menu;
 mod mutators;
+mod phrasing;
+mod readout;
+mod route;
+mod session;
+mod signature;
+mod speech;
+mod ssml;
 mod voice;
+mod watch;
+mod websocket;
 
-use clap::Parser;
+use anyhow::Context;
+use axum::middleware;
+use chrono::{DateTime, Datelike, FixedOffset};
+use chrono_tz::Tz;
+use clap::{Parser, Subcommand, ValueEnum};
 use emfcamp_schedule_api::Client as ScheduleClient;
-use metrics::describe_counter;
-use metrics_exporter_prometheus::PrometheusBuilder;
-use std::net::SocketAddr;
+use metrics::{describe_counter, describe_gauge, describe_histogram};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder};
+use std::{net::SocketAddr, path::PathBuf, sync::Arc, time::Duration};
 use tokio::net::TcpListener;
-use tracing::info;
+use tracing::{info, warn};
 use url::Url;
 
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Transport {
+    /// jambonz makes an HTTP request to a webhook for every hook
+    Http,
+    /// jambonz connects to /ws and uses the WebSocket API for the duration of each call
+    Websocket,
+}
+
 #[derive(Debug, Parser)]
 struct Cli {
     #[arg(
@@ -21,21 +51,237 @@ struct Cli {
     )]
     api_url: Url,
 
+    /// Seconds to wait for a response from the schedule API before giving up on a request
+    #[arg(long, env, default_value_t = 10)]
+    api_timeout: u64,
+
+    /// Number of times a background schedule API request that timed out, failed to connect or got
+    /// a server error (or rate limiting) status is retried
+    #[arg(long, env, default_value_t = 2)]
+    api_retries: u32,
+
+    /// Serve the schedule from a JSON snapshot file instead of the API (see the snapshot command)
+    #[arg(long, env)]
+    schedule_file: Option<PathBuf>,
+
+    /// Seconds between refreshes of the schedule from the API, or checks for changes to the
+    /// schedule and lexicon files
+    #[arg(long, env, default_value_t = 60)]
+    schedule_refresh_interval: u64,
+
+    /// Age in seconds after which callers are warned that the schedule may be out of date
+    #[arg(long, env)]
+    schedule_stale_after: Option<u64>,
+
+    /// Pretend that it is always this time (RFC 3339), to rehearse what callers will hear
+    #[arg(long, env, conflicts_with = "clock_start")]
+    clock_pin: Option<DateTime<FixedOffset>>,
+
+    /// Pretend that the application was started at this time (RFC 3339), with time advancing from
+    /// there at --clock-rate
+    #[arg(long, env)]
+    clock_start: Option<DateTime<FixedOffset>>,
+
+    /// How much faster than real time the clock advances when using --clock-start
+    #[arg(long, env, default_value_t = 1.0, requires = "clock_start", value_parser = clock::parse_rate)]
+    clock_rate: f64,
+
+    /// Timezone of the site, in which all times are spoken
+    #[arg(long, env, default_value = "Europe/London")]
+    timezone: Tz,
+
+    /// Languages offered to callers, the first of which is the default
+    #[arg(long, env, value_delimiter = ',', default_value = "en-GB,de-DE")]
+    languages: Vec<String>,
+
+    /// Prompt pack that gives the line its personality, e.g. "silly" or "serious"
+    #[arg(long, env, default_value = "silly")]
+    prompt_pack: String,
+
+    /// Directory of prompt packs to use instead of the builtin ones, as <pack>/<language>.toml
+    #[arg(long, env)]
+    prompts_dir: Option<PathBuf>,
+
+    /// Directory of event readout templates to use instead of the builtin ones, as
+    /// <language>/<readout>.j2
+    #[arg(long, env)]
+    templates_dir: Option<PathBuf>,
+
+    /// TOML file describing the menu options to use instead of the builtin menu
+    #[arg(long, env)]
+    menu_file: Option<PathBuf>,
+
+    /// Name of the event, as used in prompts
+    #[arg(long, env, default_value = "EMF")]
+    event_name: String,
+
+    /// Year of the event, as used in prompts, if not the current year
+    #[arg(long, env)]
+    event_year: Option<i32>,
+
+    /// How times of day are read out
+    #[arg(long, env, value_enum, default_value_t = phrasing::TimeStyle::Natural)]
+    time_style: phrasing::TimeStyle,
+
+    /// Send prompts as plain text rather than SSML, for synthesizers that do not support SSML
+    #[arg(long, env)]
+    plain_text_speech: bool,
+
+    /// Text-to-speech vendor
+    #[arg(long, env, default_value = "aws")]
+    tts_vendor: String,
+
+    /// Language of the text-to-speech voice
+    #[arg(long, env, default_value = "en-GB")]
+    tts_language: String,
+
+    /// Text-to-speech voice
+    #[arg(long, env, default_value = "Amy")]
+    tts_voice: String,
+
+    #[arg(long, env)]
+    tts_gender: Option<String>,
+
+    /// Text-to-speech engine, for vendors that have more than one (e.g. "neural")
+    #[arg(long, env)]
+    tts_engine: Option<String>,
+
+    /// Vendor specific text-to-speech options, as JSON
+    #[arg(long, env)]
+    tts_options: Option<serde_json::Value>,
+
+    /// Text-to-speech vendor used if the primary vendor is unavailable
+    #[arg(long, env, requires = "tts_fallback_voice")]
+    tts_fallback_vendor: Option<String>,
+
+    /// Language of the fallback text-to-speech voice, if different to --tts-language
+    #[arg(long, env, requires = "tts_fallback_vendor")]
+    tts_fallback_language: Option<String>,
+
+    /// Fallback text-to-speech voice
+    #[arg(long, env, requires = "tts_fallback_vendor")]
+    tts_fallback_voice: Option<String>,
+
+    /// Voice to use for a kind of prompt instead of --tts-voice, as <kind>=<voice> (kinds are
+    /// "announcement" and "error")
+    #[arg(long, env, value_delimiter = ',')]
+    tts_voice_override: Vec<voice::VoiceOverride>,
+
+    /// Speech-to-text vendor, for callers who speak rather than using the keypad
+    #[arg(long, env, default_value = "google")]
+    stt_vendor: String,
+
+    /// How strongly the speech-to-text vendor favours phrases callers are expected to say
+    #[arg(long, env, default_value_t = 10)]
+    stt_hints_boost: i32,
+
+    /// TOML file of words and how they should be pronounced, reloaded whenever it changes
+    #[arg(long, env)]
+    lexicon_file: Option<PathBuf>,
+
     #[arg(long, env, default_value = "0.0.0.0:8000")]
     webhook_address: SocketAddr,
 
+    /// How jambonz communicates with this application
+    #[arg(long, env, value_enum, default_value_t = Transport::Http)]
+    transport: Transport,
+
+    /// Path to serve the application under, e.g. "/dialaschedule" when sharing a host with other
+    /// services
+    #[arg(long, env, value_parser = route::parse_base_path)]
+    base_path: Option<String>,
+
+    /// Absolute URL that jambonz reaches the application at, used for action hooks when it differs
+    /// from the base path, e.g. behind a reverse proxy
+    #[arg(long, env)]
+    base_url: Option<Url>,
+
     #[arg(long, env, default_value = "127.0.0.1:9090")]
     observability_address: SocketAddr,
+
+    /// Webhook secret of the jambonz application, used to verify that webhook requests came from
+    /// jambonz
+    #[arg(long, env)]
+    webhook_secret: Option<String>,
+
+    /// Maximum age in seconds of a signed webhook request before it is rejected
+    #[arg(long, env, default_value_t = 300)]
+    webhook_signature_tolerance: u64,
+
+    /// Class of callers whose number starts with a prefix, as <class>=<prefix>, used in metrics
+    /// instead of their number (the longest prefix wins, callers matching none are "other" and
+    /// those with a withheld number are "anonymous")
+    #[arg(long, env, value_delimiter = ',')]
+    caller_class: Vec<callers::CallerClassRule>,
+
+    /// Key used to hash caller numbers for counting repeat callers, so that hashes can be compared
+    /// across restarts (a random key is used if not set)
+    #[arg(long, env)]
+    caller_hash_key: Option<String>,
+
+    /// Number of times the menu is repeated when the caller does not respond before hanging up
+    #[arg(long, env, default_value_t = 2)]
+    menu_max_reprompts: usize,
+
+    /// Number of events read out before the caller is asked if they want to hear more
+    #[arg(long, env, default_value_t = 4)]
+    events_per_page: usize,
+
+    /// Seconds after the last activity on a call that its session (and, if the end of the call is
+    /// never reported, the call itself) is forgotten
+    #[arg(long, env, default_value_t = 3600)]
+    session_expiry: u64,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Save the current schedule from the API to a file, for use with --schedule-file
+    Snapshot {
+        /// File to write the schedule to
+        output: PathBuf,
+    },
+
+    /// List the words in the speakers, venues and titles of the schedule that are not in the
+    /// lexicon
+    UnknownWords,
 }
 
 #[derive(Clone)]
 struct AppState {
-    schedule_client: ScheduleClient,
+    active_calls: Arc<session::ActiveCalls>,
+    callers: Arc<callers::Callers>,
+    catalog: Arc<catalog::Catalog>,
+    clock: Arc<dyn clock::Clock>,
+    hooks: route::ActionHooks,
+    menu: Arc<menu::Menu>,
+    phrasing: phrasing::Phrasing,
+    readouts: Arc<readout::Readouts>,
+    schedule: Arc<cache::ScheduleCache>,
+    sessions: Arc<dyn session::SessionStore>,
+    voice: voice::Voice,
+    menu_max_reprompts: usize,
+    events_per_page: usize,
 }
 
+const METRIC_ACTIVE_CALLS_NAME: &str = "dialaschedule_active_calls";
 const METRIC_API_ERRORS_NAME: &str = "dialaschedule_api_errors_total";
+const METRIC_API_LATENCY_NAME: &str = "dialaschedule_api_request_duration_seconds";
+const METRIC_CALL_DURATION_NAME: &str = "dialaschedule_call_duration_seconds";
+const METRIC_CALL_FUNNEL_NAME: &str = "dialaschedule_call_funnel_total";
 const METRIC_CALLS_NAME: &str = "dialaschedule_calls_total";
+const METRIC_HANDLER_ERRORS_NAME: &str = "dialaschedule_handler_errors_total";
+const METRIC_MENU_SELECTIONS_NAME: &str = "dialaschedule_menu_selections_total";
+const METRIC_NO_INPUT_NAME: &str = "dialaschedule_no_input_total";
+const METRIC_REPEAT_CALLS_NAME: &str = "dialaschedule_repeat_calls_total";
 const METRIC_REQUESTS_NAME: &str = "dialaschedule_requests_total";
+const METRIC_RESPONSE_EVENTS_NAME: &str = "dialaschedule_response_events";
+const METRIC_SCHEDULE_AGE_NAME: &str = "dialaschedule_schedule_age_seconds";
+const METRIC_SCHEDULE_REFRESH_FAILURES_NAME: &str = "dialaschedule_schedule_refresh_failures";
+const METRIC_SIGNATURE_REJECTIONS_NAME: &str = "dialaschedule_webhook_signature_rejections_total";
+const METRIC_UNIQUE_CALLERS_NAME: &str = "dialaschedule_unique_callers_total";
 const METRIC_USER_ERROR_NAME: &str = "dialaschedule_user_error_total";
 
 #[tokio::main]
@@ -44,37 +290,299 @@ async fn main() -> anyhow::Result<()> {
 
     tracing_subscriber::fmt::init();
 
+    let lexicon = Arc::new(match cli.lexicon_file {
+        Some(path) => lexicon::LexiconStore::file(path).await?,
+        None => lexicon::LexiconStore::builtin(),
+    });
+
+    let schedule_source = match cli.schedule_file {
+        Some(path) => {
+            info!("Serving schedule from {}", path.display());
+            cache::ScheduleSource::file(path)
+        }
+        None => cache::ScheduleSource::api(
+            ScheduleClient::new(cli.api_url.clone()),
+            Duration::from_secs(cli.api_timeout),
+            cli.api_retries,
+        ),
+    };
+
+    match cli.command {
+        Some(Command::Snapshot { output }) => {
+            let source = cache::ScheduleSource::api(
+                ScheduleClient::new(cli.api_url),
+                Duration::from_secs(cli.api_timeout),
+                cli.api_retries,
+            );
+            return source.snapshot(&output).await;
+        }
+        Some(Command::UnknownWords) => {
+            let schedule = schedule_source
+                .fetch(true)
+                .await?
+                .context("schedule source has not changed")?;
+            for word in lexicon.get(&cli.tts_language).unknown_words(&schedule) {
+                println!("{word}");
+            }
+            return Ok(());
+        }
+        None => {}
+    }
+
     // Set up metrics server
-    let builder = PrometheusBuilder::new();
+    let builder = PrometheusBuilder::new()
+        .set_buckets_for_metric(
+            Matcher::Full(METRIC_API_LATENCY_NAME.to_string()),
+            &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0],
+        )?
+        .set_buckets_for_metric(
+            Matcher::Full(METRIC_CALL_DURATION_NAME.to_string()),
+            &[10.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1200.0, 1800.0],
+        )?
+        .set_buckets_for_metric(
+            Matcher::Full(METRIC_RESPONSE_EVENTS_NAME.to_string()),
+            &[0.0, 1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0],
+        )?;
     builder
         .with_http_listener(cli.observability_address)
         .install()?;
 
+    describe_gauge!(METRIC_ACTIVE_CALLS_NAME, "Number of calls in progress");
+
     describe_counter!(
         METRIC_API_ERRORS_NAME,
-        "Total number of times a call to the event API failed"
+        "Total number of times a call to the event API failed, by class of failure"
+    );
+
+    describe_histogram!(
+        METRIC_API_LATENCY_NAME,
+        "Time taken by requests to the event API, by outcome"
+    );
+
+    describe_histogram!(METRIC_CALL_DURATION_NAME, "Length of completed calls");
+
+    describe_counter!(
+        METRIC_CALL_FUNNEL_NAME,
+        "Total number of completed calls, by the furthest stage of the call flow the caller reached"
+    );
+
+    describe_counter!(
+        METRIC_CALLS_NAME,
+        "Total number of call status updates received, by status and class of caller"
+    );
+
+    describe_counter!(
+        METRIC_HANDLER_ERRORS_NAME,
+        "Total number of call endpoint requests that failed and were answered with an apology"
+    );
+
+    describe_counter!(
+        METRIC_MENU_SELECTIONS_NAME,
+        "Total number of times a menu option was chosen, by menu and option"
+    );
+
+    describe_counter!(
+        METRIC_NO_INPUT_NAME,
+        "Total number of times a user gave no input when asked for some"
     );
 
-    describe_counter!(METRIC_CALLS_NAME, "Total number of calls received");
+    describe_counter!(
+        METRIC_REPEAT_CALLS_NAME,
+        "Total number of calls from callers who had called before, by class of caller"
+    );
 
     describe_counter!(
         METRIC_REQUESTS_NAME,
         "Total number of requests received to call endpoints"
     );
 
+    describe_histogram!(
+        METRIC_RESPONSE_EVENTS_NAME,
+        "Number of events found for each listing read to a caller, by readout"
+    );
+
+    describe_gauge!(
+        METRIC_SCHEDULE_AGE_NAME,
+        "Time since the cached schedule was last successfully refreshed"
+    );
+
+    describe_gauge!(
+        METRIC_SCHEDULE_REFRESH_FAILURES_NAME,
+        "Number of consecutive failed attempts to refresh the schedule"
+    );
+
+    describe_counter!(
+        METRIC_SIGNATURE_REJECTIONS_NAME,
+        "Total number of webhook requests rejected due to a missing or invalid signature"
+    );
+
+    describe_counter!(
+        METRIC_UNIQUE_CALLERS_NAME,
+        "Total number of distinct callers, by class of caller"
+    );
+
     describe_counter!(
         METRIC_USER_ERROR_NAME,
         "Total number of times a user entered an obviously wrong value"
     );
 
-    // Setup schedule API client
-    let schedule_client = ScheduleClient::new(cli.api_url);
+    let clock: Arc<dyn clock::Clock> = match (cli.clock_pin, cli.clock_start) {
+        (Some(now), _) => {
+            warn!("Clock is pinned to {now}");
+            Arc::new(clock::FixedClock::new(now))
+        }
+        (None, Some(start)) => {
+            warn!("Clock starts at {start} and runs at {}x", cli.clock_rate);
+            Arc::new(clock::RehearsalClock::new(start, cli.clock_rate))
+        }
+        (None, None) => Arc::new(clock::SystemClock),
+    };
+
+    // Setup schedule cache and lexicon
+    let schedule = Arc::new(cache::ScheduleCache::new(
+        schedule_source,
+        cli.schedule_stale_after.map(Duration::from_secs),
+    ));
+    schedule
+        .clone()
+        .spawn_refresh(Duration::from_secs(cli.schedule_refresh_interval));
+    lexicon
+        .clone()
+        .spawn_reload(Duration::from_secs(cli.schedule_refresh_interval));
+
+    let sessions = Arc::new(session::InMemorySessionStore::new(Duration::from_secs(
+        cli.session_expiry,
+    )));
+
+    let catalog = Arc::new(catalog::Catalog::load(
+        &cli.languages,
+        &catalog::Prompts {
+            pack: &cli.prompt_pack,
+            directory: cli.prompts_dir.as_deref(),
+            variables: [
+                ("event".to_string(), cli.event_name),
+                (
+                    "year".to_string(),
+                    cli.event_year
+                        .unwrap_or_else(|| clock.now().year())
+                        .to_string(),
+                ),
+            ]
+            .into(),
+        },
+    )?);
+
+    let readouts = Arc::new(readout::Readouts::load(
+        cli.templates_dir.as_deref(),
+        &catalog.languages()[0].code,
+    )?);
+
+    let menu = menu::Menu::load(cli.menu_file.as_deref())?;
+    menu.validate(&catalog, route::is_menu_action_hook)
+        .context("invalid menu")?;
+
+    // Over the WebSocket, hooks only identify the handler and are never requested over HTTP
+    let hooks = match (cli.transport, &cli.base_url, &cli.base_path) {
+        (Transport::Websocket, Some(_), _) => {
+            warn!("Base URL is ignored when using the WebSocket transport");
+            route::ActionHooks::default()
+        }
+        (Transport::Websocket, None, _) => route::ActionHooks::default(),
+        (Transport::Http, Some(url), _) => route::ActionHooks::new(url.as_str()),
+        (Transport::Http, None, Some(path)) => route::ActionHooks::new(path),
+        (Transport::Http, None, None) => route::ActionHooks::default(),
+    };
+
+    let state = AppState {
+        active_calls: Arc::new(session::ActiveCalls::new(Duration::from_secs(
+            cli.session_expiry,
+        ))),
+        callers: Arc::new(callers::Callers::new(cli.caller_class, cli.caller_hash_key)),
+        catalog,
+        clock,
+        hooks,
+        menu: Arc::new(menu),
+        phrasing: phrasing::Phrasing {
+            timezone: cli.timezone,
+            time_style: cli.time_style,
+        },
+        readouts,
+        schedule,
+        sessions,
+        voice: voice::Voice {
+            ssml: !cli.plain_text_speech,
+            lexicon,
+            synthesizer: jambonz::SaySynthesizer {
+                vendor: cli.tts_vendor,
+                fallback_language: cli.tts_fallback_language.or_else(|| {
+                    cli.tts_fallback_vendor
+                        .as_ref()
+                        .map(|_| cli.tts_language.clone())
+                }),
+                language: cli.tts_language,
+                gender: cli.tts_gender,
+                voice: cli.tts_voice,
+                engine: cli.tts_engine,
+                options: cli.tts_options,
+                fallback_vendor: cli.tts_fallback_vendor,
+                fallback_voice: cli.tts_fallback_voice,
+            },
+            overrides: cli
+                .tts_voice_override
+                .into_iter()
+                .map(|o| (o.kind, o.voice))
+                .collect(),
+            recognizer: jambonz::GatherRecognizer {
+                vendor: cli.stt_vendor,
+                hints_boost: cli.stt_hints_boost,
+                ..Default::default()
+            },
+        },
+        menu_max_reprompts: cli.menu_max_reprompts,
+        events_per_page: cli.events_per_page,
+    };
+    state
+        .voice
+        .validate(state.catalog.languages())
+        .context("invalid languages")?;
+
+    let mut app = handlers::build_router().layer(middleware::from_fn_with_state(
+        state.clone(),
+        fallback::recover,
+    ));
+
+    match cli.webhook_secret {
+        // Signatures are only sent with HTTP webhook requests
+        Some(_) if matches!(cli.transport, Transport::Websocket) => {
+            warn!("Webhook secret is ignored when using the WebSocket transport")
+        }
+        Some(secret) => {
+            let verifier = signature::SignatureVerifier::new(
+                secret,
+                Duration::from_secs(cli.webhook_signature_tolerance),
+            );
+            app = app.route_layer(middleware::from_fn_with_state(
+                Arc::new(verifier),
+                signature::verify_signature,
+            ));
+        }
+        None if matches!(cli.transport, Transport::Http) => {
+            warn!("No webhook secret set, webhook requests will not be verified")
+        }
+        None => {}
+    }
 
-    let state = AppState { schedule_client };
+    let app = match cli.transport {
+        Transport::Http => app.with_state(state),
+        Transport::Websocket => websocket::build_router(app.with_state(state)),
+    };
 
-    let app = handlers::build_router().with_state(state);
+    let app = match &cli.base_path {
+        Some(path) => axum::Router::new().nest(path, app),
+        None => app,
+    };
 
-    info!("Listening on {}", cli.webhook_address);
+    info!("Listening on {} ({:?})", cli.webhook_address, cli.transport);
     let listener = TcpListener::bind(&cli.webhook_address).await?;
     axum::serve(listener, app).await?;
 
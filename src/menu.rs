@@ -0,0 +1,279 @@
+use crate::{
+    catalog::{Catalog, Language},
+    ssml::Speech,
+};
+use anyhow::Context;
+use serde::Deserialize;
+use std::path::Path;
+
+const DEFAULT_MENU: &str = include_str!("../menu.toml");
+
+/// A menu of options, each selected by pressing a digit.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Menu {
+    #[serde(rename = "option")]
+    options: Vec<MenuOption>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MenuOption {
+    digit: String,
+
+    /// Message describing the option, from the prompt pack.
+    label: String,
+
+    /// Endpoint the option leads to.
+    action: Option<String>,
+
+    /// Menu the option leads to.
+    submenu: Option<Menu>,
+}
+
+/// Where selecting an option leads.
+pub(crate) enum Selection<'a> {
+    Action(&'a str),
+
+    /// A submenu, identified by the digits pressed to reach it from the main menu.
+    Submenu(String),
+}
+
+impl Menu {
+    /// Loads the menu from a file, otherwise the builtin menu.
+    pub(crate) fn load(path: Option<&Path>) -> anyhow::Result<Self> {
+        let contents = match path {
+            Some(path) => std::fs::read_to_string(path)
+                .with_context(|| format!("failed to read {}", path.display()))?,
+            None => DEFAULT_MENU.to_string(),
+        };
+
+        Ok(toml::from_str(&contents)?)
+    }
+
+    /// Checks that every option can be selected, has a label in the default language and leads
+    /// somewhere that exists.
+    pub(crate) fn validate(
+        &self,
+        catalog: &Catalog,
+        is_action: impl Fn(&str) -> bool + Copy,
+    ) -> anyhow::Result<()> {
+        self.validate_at("", catalog, is_action)
+    }
+
+    fn validate_at(
+        &self,
+        path: &str,
+        catalog: &Catalog,
+        is_action: impl Fn(&str) -> bool + Copy,
+    ) -> anyhow::Result<()> {
+        let location = if path.is_empty() {
+            "main menu".to_string()
+        } else {
+            format!("menu {path}")
+        };
+
+        if self.options.is_empty() {
+            anyhow::bail!("{location} has no options");
+        }
+
+        for (i, option) in self.options.iter().enumerate() {
+            let digit = &option.digit;
+
+            if digit.len() != 1 || !digit.chars().all(|c| c.is_ascii_digit()) {
+                anyhow::bail!("option \"{digit}\" in {location} is not a single digit");
+            }
+            if self.options[..i].iter().any(|other| other.digit == *digit) {
+                anyhow::bail!("option {digit} appears more than once in {location}");
+            }
+            if !catalog.languages()[0].contains(&option.label) {
+                anyhow::bail!(
+                    "label {} of option {digit} in {location} is not in the prompt pack",
+                    option.label
+                );
+            }
+
+            match (&option.action, &option.submenu) {
+                (Some(action), None) if is_action(action) => {}
+                (Some(action), None) => {
+                    anyhow::bail!("option {digit} in {location} leads to invalid action {action}")
+                }
+                (None, Some(submenu)) => {
+                    submenu.validate_at(&format!("{path}{digit}"), catalog, is_action)?
+                }
+                _ => anyhow::bail!(
+                    "option {digit} in {location} must have exactly one of an action or a submenu"
+                ),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Finds the submenu reached by pressing the given digits from this menu.
+    pub(crate) fn submenu(&self, path: &str) -> Option<&Menu> {
+        let mut menu = self;
+
+        for digit in path.chars() {
+            menu = menu
+                .options
+                .iter()
+                .find(|option| option.digit == digit.to_string())?
+                .submenu
+                .as_ref()?;
+        }
+
+        Some(menu)
+    }
+
+    /// Finds where pressing a digit in this menu leads, given the path to this menu.
+    pub(crate) fn select(&self, path: &str, digits: &str) -> Option<Selection<'_>> {
+        let option = self.options.iter().find(|option| option.digit == digits)?;
+
+        match (&option.action, &option.submenu) {
+            (Some(action), _) => Some(Selection::Action(action)),
+            (None, Some(_)) => Some(Selection::Submenu(format!("{path}{digits}"))),
+            (None, None) => None,
+        }
+    }
+
+    /// Reads out the options of this menu.
+    pub(crate) fn prompt(&self, language: &Language) -> Speech {
+        self.options.iter().fold(Speech::new(), |speech, option| {
+            speech.append(language.format(
+                "menu-option",
+                &[
+                    ("digit", (&option.digit).into()),
+                    ("label", language.message(&option.label)),
+                ],
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures::catalog, route::is_menu_action_hook};
+
+    const MENU: &str = r#"
+        [[option]]
+        digit = "1"
+        label = "menu-events-now"
+        action = "/call/events_now"
+
+        [[option]]
+        digit = "2"
+        label = "menu-upcoming-talks"
+
+        [[option.submenu.option]]
+        digit = "1"
+        label = "menu-upcoming-talks"
+        action = "/call/upcoming_talks_summary"
+
+        [[option.submenu.option]]
+        digit = "3"
+        label = "menu-upcoming-workshops"
+
+        [[option.submenu.option.submenu.option]]
+        digit = "1"
+        label = "menu-upcoming-workshops"
+        action = "/call/upcoming_workshops_summary"
+    "#;
+
+    #[test]
+    fn builtin_menu_is_valid() {
+        Menu::load(None)
+            .unwrap()
+            .validate(&catalog(&["en-GB"]), is_menu_action_hook)
+            .unwrap();
+    }
+
+    #[test]
+    fn invalid_menus() {
+        for (option, error) in [
+            (
+                "digit = \"12\"\nlabel = \"menu-events-now\"\naction = \"/call/events_now\"",
+                "single digit",
+            ),
+            (
+                "digit = \"2\"\nlabel = \"menu-events-now\"\naction = \"/call/events_now\"",
+                "more than once",
+            ),
+            (
+                "digit = \"3\"\nlabel = \"no-such-message\"\naction = \"/call/events_now\"",
+                "not in the prompt pack",
+            ),
+            (
+                "digit = \"3\"\nlabel = \"menu-events-now\"\naction = \"/call/menu\"",
+                "invalid action",
+            ),
+            (
+                "digit = \"3\"\nlabel = \"menu-events-now\"\naction = \"/call/next_events_at_venue\"",
+                "invalid action",
+            ),
+            ("digit = \"3\"\nlabel = \"menu-events-now\"", "exactly one"),
+        ] {
+            let menu: Menu = toml::from_str(&format!("{MENU}\n[[option]]\n{option}")).unwrap();
+            let e = menu
+                .validate(&catalog(&["en-GB"]), is_menu_action_hook)
+                .unwrap_err();
+            assert!(e.to_string().contains(error), "{e}");
+        }
+    }
+
+    #[test]
+    fn submenu() {
+        let menu: Menu = toml::from_str(MENU).unwrap();
+        menu.validate(&catalog(&["en-GB"]), is_menu_action_hook)
+            .unwrap();
+
+        assert_eq!(menu.submenu("").unwrap().options.len(), 2);
+        assert_eq!(menu.submenu("2").unwrap().options.len(), 2);
+        assert_eq!(menu.submenu("23").unwrap().options.len(), 1);
+        assert!(menu.submenu("1").is_none());
+        assert!(menu.submenu("3").is_none());
+        assert!(menu.submenu("21").is_none());
+    }
+
+    #[test]
+    fn submenu_matches_whole_digits() {
+        let menu: Menu = toml::from_str(
+            r#"
+            [[option]]
+            digit = "12"
+            label = "menu-upcoming-talks"
+
+            [[option.submenu.option]]
+            digit = "1"
+            label = "menu-upcoming-talks"
+            action = "/call/upcoming_talks_summary"
+            "#,
+        )
+        .unwrap();
+
+        assert!(menu.submenu("1").is_none());
+    }
+
+    #[test]
+    fn select() {
+        let menu: Menu = toml::from_str(MENU).unwrap();
+
+        assert!(matches!(
+            menu.select("", "1"),
+            Some(Selection::Action("/call/events_now"))
+        ));
+        assert!(matches!(
+            menu.select("", "2"),
+            Some(Selection::Submenu(path)) if path == "2"
+        ));
+
+        let submenu = menu.submenu("2").unwrap();
+        assert!(matches!(
+            submenu.select("2", "3"),
+            Some(Selection::Submenu(path)) if path == "23"
+        ));
+        assert!(submenu.select("2", "2").is_none());
+        assert!(menu.select("", "").is_none());
+    }
+}
@@ -46,3 +46,19 @@ impl Mutator for EventIsPerformance {
         events.retain(|e| matches!(e.kind, Kind::Performance));
     }
 }
+
+pub(crate) struct EventAtVenue {
+    venue: String,
+}
+
+impl EventAtVenue {
+    pub(crate) fn new(venue: String) -> Self {
+        Self { venue }
+    }
+}
+
+impl Mutator for EventAtVenue {
+    fn mutate(&self, events: &mut Vec<Event>) {
+        events.retain(|e| e.venue == self.venue);
+    }
+}
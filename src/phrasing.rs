@@ -0,0 +1,599 @@
+use crate::{catalog::Language, ssml::Speech};
+use chrono::{DateTime, Datelike, Duration, FixedOffset, Timelike};
+use chrono_tz::Tz;
+use clap::ValueEnum;
+use serde::Deserialize;
+
+/// Hour before which a time is considered part of the previous night.
+const NIGHT_ENDS_HOUR: u32 = 4;
+
+/// Hour from which a time is considered to be in the evening.
+const EVENING_STARTS_HOUR: u32 = 18;
+
+const ONES: [&str; 20] = [
+    "zero",
+    "one",
+    "two",
+    "three",
+    "four",
+    "five",
+    "six",
+    "seven",
+    "eight",
+    "nine",
+    "ten",
+    "eleven",
+    "twelve",
+    "thirteen",
+    "fourteen",
+    "fifteen",
+    "sixteen",
+    "seventeen",
+    "eighteen",
+    "nineteen",
+];
+
+const TENS: [&str; 10] = [
+    "", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety",
+];
+
+/// How times of day are read out.
+#[derive(Debug, Clone, Copy, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum TimeStyle {
+    /// The way most people say times, e.g. "half past two" or "ten to nine"
+    Natural,
+    /// The 24 hour clock, e.g. "fourteen thirty" or "oh nine hundred"
+    TwentyFourHour,
+    /// Leave it to the speech synthesizer to read "14:30"
+    Digits,
+}
+
+/// Turns times and durations into phrases that sound natural when spoken.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Phrasing {
+    pub timezone: Tz,
+    pub time_style: TimeStyle,
+}
+
+impl Phrasing {
+    /// An approximate, spoken description of a length of time, e.g. "a couple of minutes" or
+    /// "about an hour and a quarter".
+    ///
+    /// The sign of the duration is ignored.
+    pub(crate) fn duration(&self, language: &Language, duration: Duration) -> Speech {
+        let minutes = duration.num_minutes().abs();
+        let count = |n: i64| [("count", Speech::from(number(language, n)))];
+
+        match minutes {
+            0 => language.message("duration-less-than-a-minute"),
+            1 => language.message("duration-a-minute"),
+            2 | 3 => language.message("duration-a-couple-of-minutes"),
+            4..=10 => language.format("duration-minutes", &count(minutes)),
+            11..=52 => {
+                let rounded = (minutes + 2) / 5 * 5;
+                let phrase = match rounded {
+                    15 => language.message("duration-a-quarter-of-an-hour"),
+                    30 => language.message("duration-half-an-hour"),
+                    45 => language.message("duration-three-quarters-of-an-hour"),
+                    _ => language.format("duration-minutes", &count(rounded)),
+                };
+                approximately(language, phrase, rounded != minutes)
+            }
+            53..=1439 => {
+                let quarters = (minutes + 7) / 15;
+                let hours = match quarters / 4 {
+                    1 => language.message("duration-an-hour"),
+                    n => language.format("duration-hours", &count(n)),
+                };
+                let fraction = match quarters % 4 {
+                    1 => Some("duration-and-a-quarter"),
+                    2 => Some("duration-and-a-half"),
+                    3 => Some("duration-and-three-quarters"),
+                    _ => None,
+                };
+                let phrase = match fraction {
+                    Some(id) => language.format(id, &[("hours", hours)]),
+                    None => hours,
+                };
+                approximately(language, phrase, quarters * 15 != minutes)
+            }
+            _ => {
+                let days = (minutes + 720) / 1440;
+                let phrase = match days {
+                    1 => language.message("duration-a-day"),
+                    n => language.format("duration-days", &count(n)),
+                };
+                approximately(language, phrase, days * 1440 != minutes)
+            }
+        }
+    }
+
+    /// Describes how long ago something started, e.g. "just started" or "started half an hour
+    /// ago".
+    pub(crate) fn since_start(&self, language: &Language, elapsed: Duration) -> Speech {
+        if elapsed < Duration::zero() {
+            self.until_start(language, -elapsed)
+        } else if elapsed < Duration::minutes(2) {
+            language.message("just-started")
+        } else {
+            language.format(
+                "started-ago",
+                &[("duration", self.duration(language, elapsed))],
+            )
+        }
+    }
+
+    /// Describes how long until something starts, e.g. "starting in a couple of minutes".
+    pub(crate) fn until_start(&self, language: &Language, remaining: Duration) -> Speech {
+        if remaining < Duration::zero() {
+            self.since_start(language, -remaining)
+        } else if remaining < Duration::minutes(1) {
+            language.message("starting-any-moment")
+        } else {
+            language.format(
+                "starting-in",
+                &[("duration", self.duration(language, remaining))],
+            )
+        }
+    }
+
+    /// Describes how long until something finishes, e.g. "finishing in about an hour".
+    pub(crate) fn until_end(&self, language: &Language, remaining: Duration) -> Speech {
+        if remaining < Duration::zero() {
+            language.message("just-finished")
+        } else if remaining < Duration::minutes(2) {
+            language.message("about-to-finish")
+        } else {
+            language.format(
+                "finishing-in",
+                &[("duration", self.duration(language, remaining))],
+            )
+        }
+    }
+
+    /// Reads out a time of day in the configured style (unless the language has its own), in the
+    /// site timezone.
+    ///
+    /// Only digits are marked up as a time in SSML: `<say-as interpret-as="time">` expects clock
+    /// notation, so the other styles, which are already spelled out in words, are spoken as text.
+    pub(crate) fn time(&self, language: &Language, timestamp: DateTime<FixedOffset>) -> Speech {
+        let timestamp = timestamp.with_timezone(&self.timezone);
+        let hour = timestamp.hour() as i64;
+        let minute = timestamp.minute() as i64;
+
+        match language.time_style.unwrap_or(self.time_style) {
+            TimeStyle::Natural => natural_time(hour, minute).into(),
+            TimeStyle::TwentyFourHour => twenty_four_hour_time(hour, minute).into(),
+            TimeStyle::Digits => Speech::new().time(timestamp.format("%H:%M").to_string()),
+        }
+    }
+
+    /// Describes when a timestamp is relative to now, as it would be understood by someone at the
+    /// site, e.g. "at half past two", "tonight at nine o'clock", "tomorrow at ten o'clock" or "on
+    /// Sunday at eleven o'clock".
+    pub(crate) fn relative_timestamp(
+        &self,
+        language: &Language,
+        timestamp: DateTime<FixedOffset>,
+        now: DateTime<FixedOffset>,
+    ) -> Speech {
+        let time = self.time(language, timestamp);
+
+        let timestamp = timestamp.with_timezone(&self.timezone);
+        let now = now.with_timezone(&self.timezone);
+
+        let days = (timestamp.date_naive() - now.date_naive()).num_days();
+
+        let evening = timestamp.hour() >= EVENING_STARTS_HOUR;
+        let small_hours = timestamp.hour() < NIGHT_ENDS_HOUR;
+
+        let id = match days {
+            0 if evening => "tonight-at",
+            0 => "today-at",
+            // Just after midnight is still "tonight" to anyone who has not been to bed yet
+            1 if small_hours && now.hour() >= EVENING_STARTS_HOUR => "tonight-at",
+            1 => "tomorrow-at",
+            -1 => "yesterday-at",
+            _ => "weekday-at",
+        };
+
+        let weekday = &language.weekdays[timestamp.weekday().num_days_from_monday() as usize];
+
+        language.format(id, &[("time", time), ("weekday", weekday.into())])
+    }
+}
+
+fn approximately(language: &Language, phrase: Speech, approximate: bool) -> Speech {
+    if approximate {
+        language.format("duration-approximately", &[("duration", phrase)])
+    } else {
+        phrase
+    }
+}
+
+/// Spells out a number in English, for the small numbers that appear in times and durations.
+fn english_number(n: i64) -> String {
+    match n {
+        0..=19 => ONES[n as usize].to_string(),
+        20..=99 if n % 10 == 0 => TENS[n as usize / 10].to_string(),
+        20..=99 => format!("{}-{}", TENS[n as usize / 10], ONES[n as usize % 10]),
+        _ => n.to_string(),
+    }
+}
+
+/// Writes a number the way the language wants it to be read out.
+fn number(language: &Language, n: i64) -> String {
+    if language.spell_numbers {
+        english_number(n)
+    } else {
+        n.to_string()
+    }
+}
+
+fn natural_time(hour: i64, minute: i64) -> String {
+    let twelve_hour = |hour: i64| {
+        english_number(match hour % 12 {
+            0 => 12,
+            h => h,
+        })
+    };
+    let this_hour = twelve_hour(hour);
+    let next_hour = twelve_hour(hour + 1);
+
+    match (hour, minute) {
+        (0, 0) => "midnight".to_string(),
+        (12, 0) => "midday".to_string(),
+        (_, 0) => format!("{this_hour} o'clock"),
+        (_, 15) => format!("quarter past {this_hour}"),
+        (_, 30) => format!("half past {this_hour}"),
+        (_, 45) => format!("quarter to {next_hour}"),
+        (_, m) if m % 5 == 0 && m < 30 => format!("{} past {this_hour}", english_number(m)),
+        (_, m) if m % 5 == 0 => format!("{} to {next_hour}", english_number(60 - m)),
+        (_, m) if m < 10 => format!("{this_hour} oh {}", english_number(m)),
+        (_, m) => format!("{this_hour} {}", english_number(m)),
+    }
+}
+
+fn twenty_four_hour_time(hour: i64, minute: i64) -> String {
+    if (hour, minute) == (0, 0) {
+        return "midnight".to_string();
+    }
+
+    let hour = match hour {
+        0 => "zero".to_string(),
+        1..=9 => format!("oh {}", english_number(hour)),
+        _ => english_number(hour),
+    };
+
+    match minute {
+        0 => format!("{hour} hundred"),
+        1..=9 => format!("{hour} oh {}", english_number(minute)),
+        _ => format!("{hour} {}", english_number(minute)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fixtures::{language, timestamp},
+        lexicon::Lexicon,
+    };
+
+    fn phrasing(time_style: TimeStyle) -> Phrasing {
+        Phrasing {
+            timezone: chrono_tz::Europe::London,
+            time_style,
+        }
+    }
+
+    fn text(speech: Speech) -> String {
+        speech.render(false, &Lexicon::default())
+    }
+
+    #[test]
+    fn duration() {
+        let language = language("en-GB");
+        let phrasing = phrasing(TimeStyle::Natural);
+
+        for (minutes, expected) in [
+            (0, "less than a minute"),
+            (1, "a minute"),
+            (2, "a couple of minutes"),
+            (3, "a couple of minutes"),
+            (4, "four minutes"),
+            (10, "ten minutes"),
+            (11, "about ten minutes"),
+            (13, "about a quarter of an hour"),
+            (15, "a quarter of an hour"),
+            (20, "twenty minutes"),
+            (30, "half an hour"),
+            (45, "three quarters of an hour"),
+            (52, "about fifty minutes"),
+            (53, "about an hour"),
+            (59, "about an hour"),
+            (60, "an hour"),
+            (75, "an hour and a quarter"),
+            (90, "an hour and a half"),
+            (105, "an hour and three quarters"),
+            (120, "two hours"),
+            (130, "about two hours and a quarter"),
+            (1439, "about twenty-four hours"),
+            (1440, "a day"),
+            (2880, "two days"),
+            (3000, "about two days"),
+            (-1, "a minute"),
+            (-75, "an hour and a quarter"),
+        ] {
+            assert_eq!(
+                text(phrasing.duration(&language, Duration::minutes(minutes))),
+                expected,
+                "{minutes} minutes"
+            );
+        }
+
+        assert_eq!(
+            text(phrasing.duration(&language, Duration::seconds(59))),
+            "less than a minute"
+        );
+    }
+
+    #[test]
+    fn duration_without_spelled_numbers() {
+        let language = language("de-DE");
+
+        assert_eq!(
+            text(phrasing(TimeStyle::Natural).duration(&language, Duration::minutes(10))),
+            "10 Minuten"
+        );
+    }
+
+    #[test]
+    fn relative_durations() {
+        let language = language("en-GB");
+        let phrasing = phrasing(TimeStyle::Natural);
+        let minutes = Duration::minutes;
+
+        assert_eq!(
+            text(phrasing.since_start(&language, minutes(1))),
+            "just started"
+        );
+        assert_eq!(
+            text(phrasing.since_start(&language, minutes(30))),
+            "started half an hour ago"
+        );
+        assert_eq!(
+            text(phrasing.since_start(&language, minutes(-30))),
+            "starting in half an hour"
+        );
+        assert_eq!(
+            text(phrasing.until_start(&language, Duration::seconds(30))),
+            "starting any moment now"
+        );
+        assert_eq!(
+            text(phrasing.until_start(&language, minutes(2))),
+            "starting in a couple of minutes"
+        );
+        assert_eq!(
+            text(phrasing.until_start(&language, minutes(-10))),
+            "started ten minutes ago"
+        );
+        assert_eq!(
+            text(phrasing.until_end(&language, minutes(-1))),
+            "just finished"
+        );
+        assert_eq!(
+            text(phrasing.until_end(&language, minutes(1))),
+            "about to finish"
+        );
+        assert_eq!(
+            text(phrasing.until_end(&language, minutes(88))),
+            "finishing in about an hour and a half"
+        );
+    }
+
+    #[test]
+    fn natural_time() {
+        let language = language("en-GB");
+        let phrasing = phrasing(TimeStyle::Natural);
+
+        for (time, expected) in [
+            ("00:00", "midnight"),
+            ("00:30", "half past twelve"),
+            ("09:00", "nine o'clock"),
+            ("11:45", "quarter to twelve"),
+            ("12:00", "midday"),
+            ("12:15", "quarter past twelve"),
+            ("14:05", "five past two"),
+            ("14:07", "two oh seven"),
+            ("14:15", "quarter past two"),
+            ("14:23", "two twenty-three"),
+            ("14:30", "half past two"),
+            ("14:40", "twenty to three"),
+            ("14:45", "quarter to three"),
+            ("14:55", "five to three"),
+            ("23:45", "quarter to twelve"),
+            ("23:59", "eleven fifty-nine"),
+        ] {
+            let timestamp = timestamp(&format!("2024-06-01T{time}:00+01:00"));
+            assert_eq!(
+                text(phrasing.time(&language, timestamp)),
+                expected,
+                "{time}"
+            );
+        }
+    }
+
+    #[test]
+    fn twenty_four_hour_time() {
+        let language = language("en-GB");
+        let phrasing = phrasing(TimeStyle::TwentyFourHour);
+
+        for (time, expected) in [
+            ("00:00", "midnight"),
+            ("00:05", "zero oh five"),
+            ("00:30", "zero thirty"),
+            ("09:00", "oh nine hundred"),
+            ("09:07", "oh nine oh seven"),
+            ("12:00", "twelve hundred"),
+            ("14:30", "fourteen thirty"),
+            ("23:59", "twenty-three fifty-nine"),
+        ] {
+            let timestamp = timestamp(&format!("2024-06-01T{time}:00+01:00"));
+            assert_eq!(
+                text(phrasing.time(&language, timestamp)),
+                expected,
+                "{time}"
+            );
+        }
+    }
+
+    #[test]
+    fn digits_time() {
+        let phrasing = phrasing(TimeStyle::Natural);
+
+        // The German catalog always uses digits
+        assert_eq!(
+            text(phrasing.time(&language("de-DE"), timestamp("2024-06-01T13:30:00Z"))),
+            "14:30"
+        );
+    }
+
+    #[test]
+    fn only_digits_are_marked_up_as_times() {
+        let timestamp = timestamp("2024-06-01T13:30:00Z");
+        let ssml = |time_style, code| {
+            phrasing(time_style)
+                .time(&language(code), timestamp)
+                .render(true, &Lexicon::default())
+        };
+
+        assert_eq!(
+            ssml(TimeStyle::Natural, "en-GB"),
+            "<speak>half past two</speak>"
+        );
+        assert_eq!(
+            ssml(TimeStyle::TwentyFourHour, "en-GB"),
+            "<speak>fourteen thirty</speak>"
+        );
+        assert_eq!(
+            ssml(TimeStyle::Digits, "en-GB"),
+            "<speak><say-as interpret-as=\"time\" format=\"hms24\">14:30</say-as></speak>"
+        );
+    }
+
+    #[test]
+    fn time_is_in_site_timezone() {
+        let language = language("en-GB");
+        let phrasing = phrasing(TimeStyle::Natural);
+
+        assert_eq!(
+            text(phrasing.time(&language, timestamp("2024-06-01T13:30:00Z"))),
+            "half past two"
+        );
+        assert_eq!(
+            text(phrasing.time(&language, timestamp("2024-12-01T13:30:00Z"))),
+            "half past one"
+        );
+    }
+
+    #[test]
+    fn relative_timestamp() {
+        let language = language("en-GB");
+        let phrasing = phrasing(TimeStyle::Natural);
+
+        for (now, at, expected) in [
+            (
+                "2024-06-01T10:00:00+01:00",
+                "2024-06-01T14:30:00+01:00",
+                "at half past two",
+            ),
+            (
+                "2024-06-01T10:00:00+01:00",
+                "2024-06-01T20:00:00+01:00",
+                "tonight at eight o'clock",
+            ),
+            (
+                "2024-06-01T10:00:00+01:00",
+                "2024-06-02T10:00:00+01:00",
+                "tomorrow at ten o'clock",
+            ),
+            (
+                "2024-06-01T10:00:00+01:00",
+                "2024-06-04T10:00:00+01:00",
+                "on Tuesday at ten o'clock",
+            ),
+            // Across midnight
+            (
+                "2024-05-31T23:30:00+01:00",
+                "2024-06-01T00:30:00+01:00",
+                "tonight at half past twelve",
+            ),
+            (
+                "2024-05-31T12:00:00+01:00",
+                "2024-06-01T00:30:00+01:00",
+                "tomorrow at half past twelve",
+            ),
+            (
+                "2024-05-31T23:30:00+01:00",
+                "2024-06-01T09:00:00+01:00",
+                "tomorrow at nine o'clock",
+            ),
+            (
+                "2024-06-01T00:30:00+01:00",
+                "2024-05-31T22:00:00+01:00",
+                "yesterday at ten o'clock",
+            ),
+            // Midnight in the site timezone is not midnight UTC
+            (
+                "2024-05-31T21:00:00Z",
+                "2024-05-31T23:30:00Z",
+                "tonight at half past twelve",
+            ),
+            (
+                "2024-05-31T10:00:00Z",
+                "2024-05-31T23:30:00Z",
+                "tomorrow at half past twelve",
+            ),
+            // Clocks going forward at 01:00 UTC
+            (
+                "2024-03-30T23:00:00Z",
+                "2024-03-31T00:30:00Z",
+                "tonight at half past twelve",
+            ),
+            (
+                "2024-03-30T23:00:00Z",
+                "2024-03-31T01:30:00Z",
+                "tonight at half past two",
+            ),
+            (
+                "2024-03-31T00:30:00Z",
+                "2024-03-31T12:00:00Z",
+                "at one o'clock",
+            ),
+            // Clocks going back at 01:00 UTC
+            (
+                "2024-10-26T22:00:00Z",
+                "2024-10-27T00:30:00Z",
+                "tonight at half past one",
+            ),
+            (
+                "2024-10-26T22:00:00Z",
+                "2024-10-27T01:30:00Z",
+                "tonight at half past one",
+            ),
+            (
+                "2024-10-26T12:00:00Z",
+                "2024-10-27T12:00:00Z",
+                "tomorrow at midday",
+            ),
+        ] {
+            assert_eq!(
+                text(phrasing.relative_timestamp(&language, timestamp(at), timestamp(now))),
+                expected,
+                "{at} from {now}"
+            );
+        }
+    }
+}
@@ -0,0 +1,287 @@
+use crate::{catalog::Language, phrasing::Phrasing, ssml::Speech};
+use anyhow::Context;
+use chrono::{DateTime, FixedOffset};
+use emfcamp_schedule_api::schedule::event::Event;
+use minijinja::{context, Environment, Value};
+use std::path::Path;
+use tracing::error;
+
+/// The ways events are read out, each of which has a template per language.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Readout {
+    EventsNow,
+    EventsStartingSoon,
+    NextEventsEverywhere,
+    NextEventsAtVenue,
+    UpcomingTalks,
+    UpcomingWorkshops,
+    UpcomingPerformances,
+}
+
+impl Readout {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::EventsNow => "events_now",
+            Self::EventsStartingSoon => "events_starting_soon",
+            Self::NextEventsEverywhere => "next_events_everywhere",
+            Self::NextEventsAtVenue => "next_events_at_venue",
+            Self::UpcomingTalks => "upcoming_talks",
+            Self::UpcomingWorkshops => "upcoming_workshops",
+            Self::UpcomingPerformances => "upcoming_performances",
+        }
+    }
+}
+
+/// Builtin templates, by language and readout.
+const TEMPLATES: &[(&str, &str, &str)] = &[
+    (
+        "en-GB",
+        "events_now",
+        include_str!("../templates/en-GB/events_now.j2"),
+    ),
+    (
+        "en-GB",
+        "events_starting_soon",
+        include_str!("../templates/en-GB/events_starting_soon.j2"),
+    ),
+    (
+        "en-GB",
+        "next_events_everywhere",
+        include_str!("../templates/en-GB/next_events_everywhere.j2"),
+    ),
+    (
+        "en-GB",
+        "next_events_at_venue",
+        include_str!("../templates/en-GB/next_events_at_venue.j2"),
+    ),
+    (
+        "en-GB",
+        "upcoming_talks",
+        include_str!("../templates/en-GB/upcoming_talks.j2"),
+    ),
+    (
+        "en-GB",
+        "upcoming_workshops",
+        include_str!("../templates/en-GB/upcoming_workshops.j2"),
+    ),
+    (
+        "en-GB",
+        "upcoming_performances",
+        include_str!("../templates/en-GB/upcoming_performances.j2"),
+    ),
+    (
+        "de-DE",
+        "events_now",
+        include_str!("../templates/de-DE/events_now.j2"),
+    ),
+    (
+        "de-DE",
+        "events_starting_soon",
+        include_str!("../templates/de-DE/events_starting_soon.j2"),
+    ),
+    (
+        "de-DE",
+        "next_events_everywhere",
+        include_str!("../templates/de-DE/next_events_everywhere.j2"),
+    ),
+    (
+        "de-DE",
+        "next_events_at_venue",
+        include_str!("../templates/de-DE/next_events_at_venue.j2"),
+    ),
+    (
+        "de-DE",
+        "upcoming_talks",
+        include_str!("../templates/de-DE/upcoming_talks.j2"),
+    ),
+    (
+        "de-DE",
+        "upcoming_workshops",
+        include_str!("../templates/de-DE/upcoming_workshops.j2"),
+    ),
+    (
+        "de-DE",
+        "upcoming_performances",
+        include_str!("../templates/de-DE/upcoming_performances.j2"),
+    ),
+];
+
+/// Renders events as speech using MiniJinja templates.
+///
+/// Templates have access to every field of the event as `event`, along with phrases describing
+/// its timing relative to now: `start` (e.g. "tomorrow at ten o'clock"), `time` (time of day it
+/// starts), `started`, `starting`, `ending` and `length`. The `emphasis` filter emphasises text,
+/// e.g. `{{ event.title | emphasis }}`.
+pub(crate) struct Readouts {
+    environment: Environment<'static>,
+    default_language: String,
+}
+
+impl Readouts {
+    /// Loads the builtin templates, replacing any that exist in the given directory as
+    /// `<language>/<readout>.j2`.
+    pub(crate) fn load(directory: Option<&Path>, default_language: &str) -> anyhow::Result<Self> {
+        let mut environment = Environment::new();
+        environment.set_trim_blocks(true);
+        environment.set_lstrip_blocks(true);
+        environment.add_filter("emphasis", |text: String| {
+            Speech::new().emphasis(text).encode()
+        });
+
+        for (language, readout, source) in TEMPLATES {
+            let name = format!("{language}/{readout}.j2");
+
+            let source = match directory.map(|directory| directory.join(&name)) {
+                Some(path) if path.exists() => std::fs::read_to_string(&path)
+                    .with_context(|| format!("failed to read {}", path.display()))?,
+                _ => source.to_string(),
+            };
+
+            environment
+                .add_template_owned(name.clone(), source)
+                .with_context(|| format!("failed to parse template {name}"))?;
+        }
+
+        Ok(Self {
+            environment,
+            default_language: default_language.to_string(),
+        })
+    }
+
+    /// Reads out an event, in the template for the language if there is one, otherwise the
+    /// template for the default language.
+    pub(crate) fn render(
+        &self,
+        readout: Readout,
+        language: &Language,
+        phrasing: &Phrasing,
+        event: &Event,
+        now: DateTime<FixedOffset>,
+    ) -> Speech {
+        let template = self
+            .environment
+            .get_template(&format!("{}/{}.j2", language.code, readout.name()))
+            .or_else(|_| {
+                self.environment.get_template(&format!(
+                    "{}/{}.j2",
+                    self.default_language,
+                    readout.name()
+                ))
+            });
+
+        let context = context! {
+            event => Value::from_serialize(event),
+            start => phrasing.relative_timestamp(language, event.start, now).encode(),
+            time => phrasing.time(language, event.start).encode(),
+            started => phrasing.since_start(language, now - event.start).encode(),
+            starting => phrasing.until_start(language, event.start - now).encode(),
+            ending => phrasing.until_end(language, event.end - now).encode(),
+            length => phrasing.duration(language, event.end - event.start).encode(),
+        };
+
+        match template.and_then(|template| template.render(context)) {
+            Ok(text) => Speech::decode(text.trim()),
+            Err(e) => {
+                error!("Failed to render {} readout: {e}", readout.name());
+                Speech::from(&event.title)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fixtures::{self, timestamp, NOW},
+        lexicon::Lexicon,
+        phrasing::TimeStyle,
+    };
+
+    fn render(readouts: &Readouts, readout: Readout, language: &Language) -> String {
+        let phrasing = Phrasing {
+            timezone: chrono_tz::Europe::London,
+            time_style: TimeStyle::Natural,
+        };
+        let event = fixtures::event(
+            1,
+            "Soldering for beginners",
+            "Stage A",
+            "2024-06-01T11:30:00+01:00",
+            "2024-06-01T12:30:00+01:00",
+        );
+
+        readouts
+            .render(readout, language, &phrasing, &event, timestamp(NOW))
+            .render(false, &Lexicon::default())
+    }
+
+    #[test]
+    fn builtin_templates() {
+        let readouts = Readouts::load(None, "en-GB").unwrap();
+
+        assert_eq!(
+            render(&readouts, Readout::EventsNow, &fixtures::language("en-GB")),
+            "In Stage A, started half an hour ago and finishing in half an hour: Soldering for beginners by Someone."
+        );
+        assert_eq!(
+            render(&readouts, Readout::EventsNow, &fixtures::language("de-DE")),
+            "In Stage A, hat vor einer halben Stunde begonnen und endet in einer halben Stunde: Soldering for beginners von Someone."
+        );
+    }
+
+    #[test]
+    fn default_language_template() {
+        let readouts = Readouts::load(None, "en-GB").unwrap();
+
+        // A language with no templates of its own, but its own phrasing
+        let mut language = (*fixtures::language("de-DE")).clone();
+        language.code = "fr-FR".to_string();
+
+        assert_eq!(
+            render(&readouts, Readout::EventsNow, &language),
+            "In Stage A, hat vor einer halben Stunde begonnen and endet in einer halben Stunde: Soldering for beginners by Someone."
+        );
+    }
+
+    #[test]
+    fn templates_from_directory() {
+        let directory = fixtures::temp_path("templates");
+        std::fs::create_dir_all(directory.join("en-GB")).unwrap();
+        std::fs::write(
+            directory.join("en-GB/events_now.j2"),
+            "{{ event.title }} ({{ length }})",
+        )
+        .unwrap();
+        std::fs::write(
+            directory.join("en-GB/upcoming_talks.j2"),
+            "{% include \"missing.j2\" %}",
+        )
+        .unwrap();
+        let readouts = Readouts::load(Some(&directory), "en-GB").unwrap();
+        let english = fixtures::language("en-GB");
+
+        assert_eq!(
+            render(&readouts, Readout::EventsNow, &english),
+            "Soldering for beginners (an hour)"
+        );
+
+        // Templates that are not replaced are still available
+        assert!(render(&readouts, Readout::UpcomingWorkshops, &english).starts_with("Starting"));
+
+        // A template that fails to render gives just the title
+        assert_eq!(
+            render(&readouts, Readout::UpcomingTalks, &english),
+            "Soldering for beginners"
+        );
+    }
+
+    #[test]
+    fn broken_template_is_rejected() {
+        let directory = fixtures::temp_path("templates");
+        std::fs::create_dir_all(directory.join("de-DE")).unwrap();
+        std::fs::write(directory.join("de-DE/events_now.j2"), "{{ event.title").unwrap();
+
+        assert!(Readouts::load(Some(&directory), "en-GB").is_err());
+    }
+}
@@ -0,0 +1,238 @@
+use strum::{EnumIter, IntoEnumIterator};
+use url::form_urlencoded;
+
+/// Endpoints jambonz calls as a call progresses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter)]
+pub(crate) enum Route {
+    CallStatus,
+    Incoming,
+    LanguageSelection,
+    Menu,
+    MenuSelection,
+    Query,
+    Navigation,
+    EventsNow,
+    EventsStartingSoon,
+    NextEventsEverywhere,
+    NextEventsAtVenue,
+    UpcomingTalksSummary,
+    UpcomingWorkshopsSummary,
+    UpcomingPerformancesSummary,
+}
+
+impl Route {
+    /// Path of the endpoint, relative to the base path.
+    pub(crate) fn path(self) -> &'static str {
+        match self {
+            Self::CallStatus => "/call_status",
+            Self::Incoming => "/call/incoming",
+            Self::LanguageSelection => "/call/language_selection",
+            Self::Menu => "/call/menu",
+            Self::MenuSelection => "/call/menu_selection",
+            Self::Query => "/call/query",
+            Self::Navigation => "/call/navigation",
+            Self::EventsNow => "/call/events_now",
+            Self::EventsStartingSoon => "/call/events_starting_soon",
+            Self::NextEventsEverywhere => "/call/next_events_everywhere",
+            Self::NextEventsAtVenue => "/call/next_events_at_venue",
+            Self::UpcomingTalksSummary => "/call/upcoming_talks_summary",
+            Self::UpcomingWorkshopsSummary => "/call/upcoming_workshops_summary",
+            Self::UpcomingPerformancesSummary => "/call/upcoming_performances_summary",
+        }
+    }
+
+    /// Finds the route a hook (relative to the base path) leads to, ignoring any query string.
+    pub(crate) fn from_hook(hook: &str) -> Option<Self> {
+        let path = hook.split('?').next().unwrap_or_default();
+        Self::iter().find(|route| route.path() == path)
+    }
+
+    /// Whether a menu option may lead to the route.
+    pub(crate) fn is_menu_action(self) -> bool {
+        matches!(
+            self,
+            Self::Query
+                | Self::EventsNow
+                | Self::EventsStartingSoon
+                | Self::NextEventsEverywhere
+                | Self::NextEventsAtVenue
+                | Self::UpcomingTalksSummary
+                | Self::UpcomingWorkshopsSummary
+                | Self::UpcomingPerformancesSummary
+        )
+    }
+
+    /// Query parameters that every hook to the route must have.
+    pub(crate) fn required_query(self) -> &'static [&'static str] {
+        match self {
+            Self::NextEventsAtVenue => &["venue"],
+            _ => &[],
+        }
+    }
+}
+
+/// Whether a menu option may lead to a hook, i.e. it is to a route that may be a menu action and
+/// has the query parameters the route requires.
+pub(crate) fn is_menu_action_hook(hook: &str) -> bool {
+    let Some(route) = Route::from_hook(hook) else {
+        return false;
+    };
+    let query = hook.split_once('?').map_or("", |(_, query)| query);
+
+    route.is_menu_action()
+        && route.required_query().iter().all(|required| {
+            form_urlencoded::parse(query.as_bytes())
+                .any(|(name, value)| name == *required && !value.is_empty())
+        })
+}
+
+/// Builds the action hooks given to jambonz, which may need a prefix when the service is mounted
+/// under a base path or reached through a reverse proxy.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ActionHooks {
+    base: String,
+}
+
+impl ActionHooks {
+    /// Creates hooks prefixed with a base path (e.g. "/dialaschedule") or absolute base URL (e.g.
+    /// "https://example.org/dialaschedule").
+    pub(crate) fn new(base: &str) -> Self {
+        Self {
+            base: base.trim_end_matches('/').to_string(),
+        }
+    }
+
+    pub(crate) fn get(&self, route: Route) -> String {
+        format!("{}{}", self.base, route.path())
+    }
+
+    /// Hook for a route with query parameters, e.g. the venue to list events at.
+    pub(crate) fn with_query(&self, route: Route, query: &[(&str, &str)]) -> String {
+        let query = form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(query)
+            .finish();
+        format!("{}?{query}", self.get(route))
+    }
+
+    /// Hook for a path and query relative to the base path, e.g. as visited earlier in the call.
+    pub(crate) fn resolve(&self, hook: &str) -> String {
+        format!("{}{hook}", self.base)
+    }
+}
+
+/// Checks that a base path can be mounted, i.e. it starts but does not end with a slash.
+pub(crate) fn parse_base_path(path: &str) -> Result<String, String> {
+    if !path.starts_with('/') || path.ends_with('/') {
+        Err("must start with a slash and not end with one, e.g. \"/dialaschedule\"".to_string())
+    } else {
+        Ok(path.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routes_round_trip() {
+        for route in Route::iter() {
+            assert_eq!(Route::from_hook(route.path()), Some(route), "{route:?}");
+            assert_eq!(
+                Route::from_hook(&format!("{}?venue=Stage%20A", route.path())),
+                Some(route),
+                "{route:?}"
+            );
+        }
+
+        assert_eq!(Route::from_hook("/call/nothing"), None);
+        assert_eq!(Route::from_hook("/dialaschedule/call/menu"), None);
+    }
+
+    #[test]
+    fn menu_action_hooks() {
+        assert!(is_menu_action_hook("/call/events_now"));
+        assert!(is_menu_action_hook(
+            "/call/next_events_at_venue?venue=Stage%20A"
+        ));
+        assert!(is_menu_action_hook(
+            "/call/next_events_at_venue?day=1&venue=Stage+A"
+        ));
+
+        assert!(!is_menu_action_hook("/call/menu"));
+        assert!(!is_menu_action_hook("/call/nothing"));
+        assert!(!is_menu_action_hook("/call/next_events_at_venue"));
+        assert!(!is_menu_action_hook("/call/next_events_at_venue?venue="));
+        assert!(!is_menu_action_hook("/call/next_events_at_venue?stage=A"));
+    }
+
+    #[test]
+    fn paths_are_unique() {
+        for (i, route) in Route::iter().enumerate() {
+            assert!(
+                Route::iter()
+                    .skip(i + 1)
+                    .all(|other| other.path() != route.path()),
+                "{route:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn hooks_without_base() {
+        let hooks = ActionHooks::default();
+
+        assert_eq!(hooks.get(Route::Menu), "/call/menu");
+        assert_eq!(
+            hooks.with_query(Route::NextEventsAtVenue, &[("venue", "Stage A & B")]),
+            "/call/next_events_at_venue?venue=Stage+A+%26+B"
+        );
+        assert_eq!(hooks.resolve("/call/menu?path=2"), "/call/menu?path=2");
+    }
+
+    #[test]
+    fn hooks_with_base_path() {
+        let hooks = ActionHooks::new("/dialaschedule/");
+
+        assert_eq!(hooks.get(Route::Menu), "/dialaschedule/call/menu");
+        assert_eq!(
+            hooks.with_query(Route::Menu, &[("path", "23")]),
+            "/dialaschedule/call/menu?path=23"
+        );
+        assert_eq!(
+            hooks.resolve("/call/menu?path=2"),
+            "/dialaschedule/call/menu?path=2"
+        );
+    }
+
+    #[test]
+    fn hooks_with_base_url() {
+        let hooks = ActionHooks::new("https://example.org/dialaschedule");
+
+        assert_eq!(
+            hooks.get(Route::Navigation),
+            "https://example.org/dialaschedule/call/navigation"
+        );
+        assert_eq!(
+            hooks.with_query(Route::NextEventsAtVenue, &[("venue", "Stage A")]),
+            "https://example.org/dialaschedule/call/next_events_at_venue?venue=Stage+A"
+        );
+        assert_eq!(
+            hooks.resolve("/call/events_now"),
+            "https://example.org/dialaschedule/call/events_now"
+        );
+
+        let url = url::Url::parse("https://example.org").unwrap();
+        assert_eq!(
+            ActionHooks::new(url.as_str()).get(Route::Menu),
+            "https://example.org/call/menu"
+        );
+    }
+
+    #[test]
+    fn base_path() {
+        assert_eq!(parse_base_path("/dialaschedule").unwrap(), "/dialaschedule");
+        assert!(parse_base_path("dialaschedule").is_err());
+        assert!(parse_base_path("/dialaschedule/").is_err());
+        assert!(parse_base_path("/").is_err());
+    }
+}
@@ -0,0 +1,350 @@
+use crate::jambonz::{CallStatus, Verb};
+use metrics::gauge;
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Everything remembered about a single call.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Session {
+    /// Hooks the caller has navigated to, oldest first.
+    pub history: Vec<String>,
+
+    /// The verbs most recently spoken to the caller.
+    pub last_response: Vec<Verb>,
+
+    /// Items of a list that have not yet been read to the caller, see `handlers::next_page`.
+    pub remaining: Vec<Verb>,
+
+    /// Language the caller has chosen, if they have chosen one.
+    pub language: Option<String>,
+
+    /// Number of consecutive times the caller has been asked for input without giving any.
+    pub retries: usize,
+}
+
+/// Points in the call flow, in the order callers reach them, for measuring how far callers get
+/// before hanging up.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Stage {
+    #[default]
+    Started,
+    Menu,
+    Selected,
+    Content,
+    MorePages,
+}
+
+impl Stage {
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            Self::Started => "started",
+            Self::Menu => "menu",
+            Self::Selected => "selected",
+            Self::Content => "content",
+            Self::MorePages => "more_pages",
+        }
+    }
+}
+
+impl Session {
+    /// Records a visit to a hook, unless it is the hook the caller is already on (i.e. a reprompt).
+    pub(crate) fn visit(&mut self, hook: &str) {
+        if self.history.last().map(String::as_str) != Some(hook) {
+            self.history.push(hook.to_string());
+        }
+    }
+
+    /// The hook visited before the current one, removing the current one from the history.
+    pub(crate) fn back(&mut self) -> Option<String> {
+        self.history.pop();
+        self.history.last().cloned()
+    }
+}
+
+/// Storage for call sessions, keyed by call SID.
+pub(crate) trait SessionStore: Send + Sync {
+    fn get(&self, call_sid: &str) -> Option<Session>;
+
+    fn put(&self, call_sid: &str, session: Session);
+
+    /// Modifies the session for a call, creating it if it does not already exist, without any other
+    /// change to the session being lost in the meantime.
+    fn modify(&self, call_sid: &str, f: &mut dyn FnMut(&mut Session));
+
+    fn remove(&self, call_sid: &str);
+}
+
+impl dyn SessionStore {
+    /// Modifies the session for a call, creating it if it does not already exist.
+    pub(crate) fn update<R>(&self, call_sid: &str, f: impl FnOnce(&mut Session) -> R) -> R {
+        let mut f = Some(f);
+        let mut result = None;
+        self.modify(call_sid, &mut |session| {
+            result = f.take().map(|f| f(session));
+        });
+        result.expect("session store should call the modification once")
+    }
+}
+
+/// Keeps sessions in memory, forgetting any that have not been updated for a while in case jambonz
+/// never tells us that a call has ended.
+pub(crate) struct InMemorySessionStore {
+    expiry: Duration,
+    sessions: Mutex<HashMap<String, (Instant, Session)>>,
+}
+
+impl InMemorySessionStore {
+    pub(crate) fn new(expiry: Duration) -> Self {
+        Self {
+            expiry,
+            sessions: Default::default(),
+        }
+    }
+}
+
+impl SessionStore for InMemorySessionStore {
+    fn get(&self, call_sid: &str) -> Option<Session> {
+        let sessions = self
+            .sessions
+            .lock()
+            .expect("session lock should not be poisoned");
+        sessions
+            .get(call_sid)
+            .filter(|(updated, _)| updated.elapsed() < self.expiry)
+            .map(|(_, session)| session.clone())
+    }
+
+    fn put(&self, call_sid: &str, session: Session) {
+        let mut sessions = self
+            .sessions
+            .lock()
+            .expect("session lock should not be poisoned");
+        sessions.retain(|_, (updated, _)| updated.elapsed() < self.expiry);
+        sessions.insert(call_sid.to_string(), (Instant::now(), session));
+    }
+
+    fn modify(&self, call_sid: &str, f: &mut dyn FnMut(&mut Session)) {
+        let mut sessions = self
+            .sessions
+            .lock()
+            .expect("session lock should not be poisoned");
+        sessions.retain(|_, (updated, _)| updated.elapsed() < self.expiry);
+
+        let (updated, session) = sessions
+            .entry(call_sid.to_string())
+            .or_insert_with(|| (Instant::now(), Session::default()));
+        f(session);
+        *updated = Instant::now();
+    }
+
+    fn remove(&self, call_sid: &str) {
+        let mut sessions = self
+            .sessions
+            .lock()
+            .expect("session lock should not be poisoned");
+        sessions.remove(call_sid);
+    }
+}
+
+/// Tracks which calls are in progress, from call status updates, and how far into the call flow
+/// each caller has got.
+///
+/// This is kept apart from sessions, which are created by any request and forgotten as soon as a
+/// call ends. Calls are forgotten after the same time without activity as sessions, in case the
+/// status update for the end of a call is never received.
+pub(crate) struct ActiveCalls {
+    expiry: Duration,
+    calls: Mutex<HashMap<String, ActiveCall>>,
+}
+
+struct ActiveCall {
+    updated: Instant,
+
+    /// Whether jambonz has reported the call as in progress.
+    in_progress: bool,
+
+    /// Furthest the caller has got into the call flow.
+    stage: Stage,
+}
+
+impl ActiveCalls {
+    pub(crate) fn new(expiry: Duration) -> Self {
+        Self {
+            expiry,
+            calls: Default::default(),
+        }
+    }
+
+    /// Records a call status update, giving the furthest stage the caller reached if a call that
+    /// was being tracked has ended.
+    pub(crate) fn update(&self, call_sid: &str, status: &CallStatus) -> Option<Stage> {
+        self.modify(|calls| match status {
+            CallStatus::Completed
+            | CallStatus::Failed
+            | CallStatus::Busy
+            | CallStatus::NoAnswer => calls.remove(call_sid).map(|call| call.stage),
+            status => {
+                let call = Self::get(calls, call_sid);
+                call.in_progress |= matches!(status, CallStatus::InProgress);
+                None
+            }
+        })
+    }
+
+    /// Records that the caller has reached a stage, unless they have already got further.
+    pub(crate) fn reach(&self, call_sid: &str, stage: Stage) {
+        self.modify(|calls| {
+            let call = Self::get(calls, call_sid);
+            call.stage = call.stage.max(stage);
+        })
+    }
+
+    fn get<'a>(calls: &'a mut HashMap<String, ActiveCall>, call_sid: &str) -> &'a mut ActiveCall {
+        let call = calls
+            .entry(call_sid.to_string())
+            .or_insert_with(|| ActiveCall {
+                updated: Instant::now(),
+                in_progress: false,
+                stage: Stage::default(),
+            });
+        call.updated = Instant::now();
+        call
+    }
+
+    /// Modifies the calls once expired calls are forgotten, then updates the number of calls in
+    /// progress.
+    fn modify<R>(&self, f: impl FnOnce(&mut HashMap<String, ActiveCall>) -> R) -> R {
+        let mut calls = self
+            .calls
+            .lock()
+            .expect("active calls lock should not be poisoned");
+        calls.retain(|_, call| call.updated.elapsed() < self.expiry);
+
+        let result = f(&mut calls);
+
+        let in_progress = calls.values().filter(|call| call.in_progress).count();
+        gauge!(crate::METRIC_ACTIVE_CALLS_NAME).set(in_progress as f64);
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn update_creates_session() {
+        let store: Arc<dyn SessionStore> =
+            Arc::new(InMemorySessionStore::new(Duration::from_secs(60)));
+
+        store.update("abc", |session| session.retries += 1);
+
+        assert_eq!(store.get("abc").unwrap().retries, 1);
+    }
+
+    #[test]
+    fn concurrent_updates_are_not_lost() {
+        let store: Arc<dyn SessionStore> =
+            Arc::new(InMemorySessionStore::new(Duration::from_secs(60)));
+
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let store = store.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..100 {
+                        store.update("abc", |session| session.retries += 1);
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert_eq!(store.get("abc").unwrap().retries, 800);
+    }
+
+    #[test]
+    fn expired_sessions_are_replaced() {
+        let store: Arc<dyn SessionStore> =
+            Arc::new(InMemorySessionStore::new(Duration::from_millis(50)));
+
+        store.update("abc", |session| session.retries += 1);
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(store.get("abc").is_none());
+
+        store.update("abc", |session| session.retries += 1);
+        assert_eq!(store.get("abc").unwrap().retries, 1);
+    }
+
+    fn in_progress(calls: &ActiveCalls) -> usize {
+        calls.modify(|calls| calls.values().filter(|call| call.in_progress).count())
+    }
+
+    #[test]
+    fn active_calls() {
+        let calls = ActiveCalls::new(Duration::from_secs(60));
+
+        assert_eq!(calls.update("abc", &CallStatus::Ringing), None);
+        assert_eq!(in_progress(&calls), 0);
+
+        assert_eq!(calls.update("abc", &CallStatus::InProgress), None);
+        assert_eq!(calls.update("xyz", &CallStatus::InProgress), None);
+        assert_eq!(in_progress(&calls), 2);
+
+        assert_eq!(
+            calls.update("abc", &CallStatus::Completed),
+            Some(Stage::Started)
+        );
+        assert_eq!(in_progress(&calls), 1);
+    }
+
+    #[test]
+    fn stage_is_kept_until_call_ends() {
+        let calls = ActiveCalls::new(Duration::from_secs(60));
+
+        calls.update("abc", &CallStatus::InProgress);
+        calls.reach("abc", Stage::Content);
+        calls.reach("abc", Stage::Menu);
+
+        assert_eq!(
+            calls.update("abc", &CallStatus::Completed),
+            Some(Stage::Content)
+        );
+
+        // A repeated status update for a call that has already ended is not counted again
+        assert_eq!(calls.update("abc", &CallStatus::Completed), None);
+    }
+
+    #[test]
+    fn stage_can_be_reached_before_call_is_in_progress() {
+        let calls = ActiveCalls::new(Duration::from_secs(60));
+
+        calls.reach("abc", Stage::Menu);
+        assert_eq!(in_progress(&calls), 0);
+
+        calls.update("abc", &CallStatus::InProgress);
+        assert_eq!(in_progress(&calls), 1);
+        assert_eq!(
+            calls.update("abc", &CallStatus::NoAnswer),
+            Some(Stage::Menu)
+        );
+    }
+
+    #[test]
+    fn active_calls_expire() {
+        let calls = ActiveCalls::new(Duration::from_millis(50));
+
+        calls.update("abc", &CallStatus::InProgress);
+        calls.reach("abc", Stage::Content);
+        std::thread::sleep(Duration::from_millis(100));
+
+        assert_eq!(in_progress(&calls), 0);
+        assert_eq!(calls.update("abc", &CallStatus::Completed), None);
+    }
+}
@@ -0,0 +1,336 @@
+use axum::{
+    body::{Body, Bytes},
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use hmac::{Hmac, Mac};
+use http_body_util::LengthLimitError;
+use metrics::counter;
+use sha2::Sha256;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tracing::warn;
+
+const SIGNATURE_HEADER: &str = "jambonz-signature";
+
+/// Largest webhook request body that is read, far more than jambonz ever sends.
+pub(crate) const MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// Reads a request body of up to [`MAX_BODY_BYTES`], otherwise gives the response to reject the
+/// request with.
+pub(crate) async fn read_body(body: Body) -> Result<Bytes, Response> {
+    axum::body::to_bytes(body, MAX_BODY_BYTES)
+        .await
+        .map_err(|e| {
+            let e = e.into_inner();
+            if e.is::<LengthLimitError>() {
+                warn!("Webhook body is larger than {MAX_BODY_BYTES} bytes");
+                StatusCode::PAYLOAD_TOO_LARGE.into_response()
+            } else {
+                warn!("Failed to read webhook body: {e}");
+                StatusCode::BAD_REQUEST.into_response()
+            }
+        })
+}
+
+/// Verifies the `Jambonz-Signature` header that jambonz attaches to webhook requests.
+///
+/// The header has the form `t=<unix timestamp>,v1=<hex HMAC-SHA256>`, where the HMAC is computed
+/// over `<timestamp>.<request body>` using the webhook secret of the jambonz application.
+pub(crate) struct SignatureVerifier {
+    secret: Vec<u8>,
+    tolerance: Duration,
+
+    /// Signatures already accepted, with their timestamp, so that a captured request cannot be
+    /// replayed within the tolerance window.
+    seen: Mutex<HashMap<Vec<u8>, u64>>,
+}
+
+#[derive(Debug)]
+enum Rejection {
+    Missing,
+    Malformed,
+    Expired,
+    Invalid,
+    Replayed,
+}
+
+impl Rejection {
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Missing => "missing",
+            Self::Malformed => "malformed",
+            Self::Expired => "expired",
+            Self::Invalid => "invalid",
+            Self::Replayed => "replayed",
+        }
+    }
+}
+
+impl SignatureVerifier {
+    pub(crate) fn new(secret: String, tolerance: Duration) -> Self {
+        Self {
+            secret: secret.into_bytes(),
+            tolerance,
+            seen: Default::default(),
+        }
+    }
+
+    fn verify(&self, header: Option<&str>, body: &[u8]) -> Result<(), Rejection> {
+        let header = header.ok_or(Rejection::Missing)?;
+
+        let mut timestamp = None;
+        let mut signatures = Vec::new();
+
+        for part in header.split(',') {
+            match part.trim().split_once('=') {
+                Some(("t", value)) => {
+                    timestamp = Some(value.parse::<u64>().map_err(|_| Rejection::Malformed)?)
+                }
+                Some(("v1", value)) => {
+                    signatures.push(hex::decode(value).map_err(|_| Rejection::Malformed)?)
+                }
+                _ => {}
+            }
+        }
+
+        let timestamp = timestamp.ok_or(Rejection::Malformed)?;
+        if signatures.is_empty() {
+            return Err(Rejection::Malformed);
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time should be after the unix epoch")
+            .as_secs();
+        if now.abs_diff(timestamp) > self.tolerance.as_secs() {
+            return Err(Rejection::Expired);
+        }
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.secret)
+            .expect("HMAC should accept a key of any length");
+        mac.update(format!("{timestamp}.").as_bytes());
+        mac.update(body);
+
+        let signature = signatures
+            .into_iter()
+            .find(|signature| mac.clone().verify_slice(signature).is_ok())
+            .ok_or(Rejection::Invalid)?;
+
+        let mut seen = self
+            .seen
+            .lock()
+            .expect("seen signatures lock should not be poisoned");
+        seen.retain(|_, t| now.abs_diff(*t) <= self.tolerance.as_secs());
+        if seen.insert(signature, timestamp).is_some() {
+            return Err(Rejection::Replayed);
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) async fn verify_signature(
+    State(verifier): State<Arc<SignatureVerifier>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let (parts, body) = request.into_parts();
+
+    // The body is read before the request is known to come from jambonz, so its size is limited
+    let body = match read_body(body).await {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+
+    let header = parts
+        .headers
+        .get(SIGNATURE_HEADER)
+        .and_then(|value| value.to_str().ok());
+
+    match verifier.verify(header, &body) {
+        Ok(()) => next.run(Request::from_parts(parts, Body::from(body))).await,
+        Err(rejection) => {
+            warn!(
+                "Rejected webhook request to {} ({rejection:?} signature)",
+                parts.uri
+            );
+            counter!(crate::METRIC_SIGNATURE_REJECTIONS_NAME, "reason" => rejection.label())
+                .increment(1);
+            StatusCode::UNAUTHORIZED.into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::post, Router};
+    use tower::ServiceExt;
+
+    const SECRET: &str = "wh_secret_test";
+
+    fn verifier() -> SignatureVerifier {
+        SignatureVerifier::new(SECRET.to_string(), Duration::from_secs(300))
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    fn sign(secret: &str, timestamp: u64, body: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(format!("{timestamp}.").as_bytes());
+        mac.update(body);
+        format!(
+            "t={timestamp},v1={}",
+            hex::encode(mac.finalize().into_bytes())
+        )
+    }
+
+    #[test]
+    fn valid() {
+        let body = br#"{"call_sid":"abc"}"#;
+        let header = sign(SECRET, now(), body);
+
+        assert!(verifier().verify(Some(&header), body).is_ok());
+    }
+
+    #[test]
+    fn valid_with_several_signatures() {
+        let body = br#"{"call_sid":"abc"}"#;
+        let timestamp = now();
+        let header = format!(
+            "{},v1={}",
+            sign(SECRET, timestamp, body),
+            hex::encode([0u8; 32])
+        );
+
+        assert!(verifier().verify(Some(&header), body).is_ok());
+    }
+
+    #[test]
+    fn missing() {
+        assert!(matches!(
+            verifier().verify(None, b"{}"),
+            Err(Rejection::Missing)
+        ));
+    }
+
+    #[test]
+    fn malformed() {
+        let verifier = verifier();
+
+        for header in [
+            "",
+            "t=notanumber,v1=00",
+            &format!("t={},v1=nothex", now()),
+            &format!("t={}", now()),
+            "v1=00",
+        ] {
+            assert!(
+                matches!(
+                    verifier.verify(Some(header), b"{}"),
+                    Err(Rejection::Malformed)
+                ),
+                "{header}"
+            );
+        }
+    }
+
+    #[test]
+    fn wrong_hmac() {
+        let body = br#"{"call_sid":"abc"}"#;
+        let verifier = verifier();
+
+        let header = sign("some other secret", now(), body);
+        assert!(matches!(
+            verifier.verify(Some(&header), body),
+            Err(Rejection::Invalid)
+        ));
+
+        let header = sign(SECRET, now(), body);
+        assert!(matches!(
+            verifier.verify(Some(&header), br#"{"call_sid":"xyz"}"#),
+            Err(Rejection::Invalid)
+        ));
+    }
+
+    #[test]
+    fn outside_tolerance() {
+        let body = br#"{"call_sid":"abc"}"#;
+        let verifier = verifier();
+
+        for timestamp in [now() - 301, now() + 301] {
+            let header = sign(SECRET, timestamp, body);
+            assert!(matches!(
+                verifier.verify(Some(&header), body),
+                Err(Rejection::Expired)
+            ));
+        }
+    }
+
+    #[test]
+    fn replayed() {
+        let body = br#"{"call_sid":"abc"}"#;
+        let header = sign(SECRET, now(), body);
+        let verifier = verifier();
+
+        assert!(verifier.verify(Some(&header), body).is_ok());
+        assert!(matches!(
+            verifier.verify(Some(&header), body),
+            Err(Rejection::Replayed)
+        ));
+    }
+
+    async fn status(body: Vec<u8>, header: Option<String>) -> StatusCode {
+        let app = Router::new()
+            .route("/call/menu", post(|| async { "ok" }))
+            .route_layer(axum::middleware::from_fn_with_state(
+                Arc::new(verifier()),
+                verify_signature,
+            ));
+
+        let mut request = Request::post("/call/menu");
+        if let Some(header) = header {
+            request = request.header(SIGNATURE_HEADER, header);
+        }
+
+        app.oneshot(request.body(Body::from(body)).unwrap())
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn middleware_passes_signed_requests() {
+        let body = br#"{"call_sid":"abc"}"#.to_vec();
+        let header = sign(SECRET, now(), &body);
+
+        assert_eq!(status(body, Some(header)).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn middleware_rejects_unsigned_requests() {
+        assert_eq!(status(b"{}".to_vec(), None).await, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn middleware_rejects_large_bodies() {
+        let body = vec![b' '; MAX_BODY_BYTES + 1];
+        let header = sign(SECRET, now(), &body);
+
+        assert_eq!(
+            status(body, Some(header)).await,
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+    }
+}
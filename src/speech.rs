@@ -0,0 +1,283 @@
+use crate::route::{ActionHooks, Route};
+use emfcamp_schedule_api::schedule::Schedule;
+use serde::Deserialize;
+
+/// Confidence below which a transcript is treated as not having been understood.
+pub(crate) const MIN_CONFIDENCE: f64 = 0.4;
+
+/// Most hints given to the recognizer.
+const MAX_HINTS: usize = 100;
+
+/// Words and phrases that callers may say in one language, in lowercase.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct Keywords {
+    /// Phrases that are always useful to the recognizer at the menu, regardless of what is in the
+    /// schedule.
+    hints: Vec<String>,
+
+    /// Phrases for moving around the call once some content has been read out.
+    navigation_hints: Vec<String>,
+
+    events_now: Vec<String>,
+    events_starting_soon: Vec<String>,
+    next_events_everywhere: Vec<String>,
+    upcoming_talks: Vec<String>,
+    upcoming_workshops: Vec<String>,
+    upcoming_performances: Vec<String>,
+    repeat: Vec<String>,
+    back: Vec<String>,
+}
+
+/// A request to move around the call rather than to hear something new.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Navigation {
+    Repeat,
+    Back,
+}
+
+impl Navigation {
+    pub(crate) fn from_transcript(transcript: &str, keywords: &Keywords) -> Option<Self> {
+        let words = Words::new(transcript);
+
+        if words.mentions(&keywords.repeat) {
+            Some(Self::Repeat)
+        } else if words.mentions(&keywords.back) {
+            Some(Self::Back)
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn hints(keywords: &Keywords) -> Vec<String> {
+        keywords.navigation_hints.clone()
+    }
+}
+
+/// Something a caller asked for by voice.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Intent {
+    EventsNow,
+    EventsStartingSoon,
+    NextEventsEverywhere,
+    NextEventsAtVenue(String),
+    UpcomingTalks,
+    UpcomingWorkshops,
+    UpcomingPerformances,
+}
+
+impl Intent {
+    /// Attempts to work out what the caller wants from what they said.
+    ///
+    /// A mention of a known venue takes priority, as "what's next at Stage A" would otherwise also
+    /// match the more general "what's next" query.
+    pub(crate) fn from_transcript(
+        transcript: &str,
+        venues: &[String],
+        keywords: &Keywords,
+    ) -> Option<Self> {
+        let words = Words::new(transcript);
+
+        if let Some(venue) = venues
+            .iter()
+            .find(|venue| words.mentions(&[Words::new(venue).0]))
+        {
+            return Some(Self::NextEventsAtVenue(venue.clone()));
+        }
+
+        if words.mentions(&keywords.upcoming_workshops) {
+            Some(Self::UpcomingWorkshops)
+        } else if words.mentions(&keywords.upcoming_talks) {
+            Some(Self::UpcomingTalks)
+        } else if words.mentions(&keywords.upcoming_performances) {
+            Some(Self::UpcomingPerformances)
+        } else if words.mentions(&keywords.events_starting_soon) {
+            Some(Self::EventsStartingSoon)
+        } else if words.mentions(&keywords.next_events_everywhere) {
+            Some(Self::NextEventsEverywhere)
+        } else if words.mentions(&keywords.events_now) {
+            Some(Self::EventsNow)
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn action_hook(&self, hooks: &ActionHooks) -> String {
+        match self {
+            Self::EventsNow => hooks.get(Route::EventsNow),
+            Self::EventsStartingSoon => hooks.get(Route::EventsStartingSoon),
+            Self::NextEventsEverywhere => hooks.get(Route::NextEventsEverywhere),
+            Self::NextEventsAtVenue(venue) => {
+                hooks.with_query(Route::NextEventsAtVenue, &[("venue", venue)])
+            }
+            Self::UpcomingTalks => hooks.get(Route::UpcomingTalksSummary),
+            Self::UpcomingWorkshops => hooks.get(Route::UpcomingWorkshopsSummary),
+            Self::UpcomingPerformances => hooks.get(Route::UpcomingPerformancesSummary),
+        }
+    }
+}
+
+/// The words of a transcript, lowercased and separated by single spaces, so that keywords only
+/// match whole words and phrases.
+struct Words(String);
+
+impl Words {
+    fn new(transcript: &str) -> Self {
+        let words: Vec<&str> = transcript
+            .split(|c: char| !c.is_alphanumeric() && c != '\'')
+            .filter(|word| !word.is_empty())
+            .collect();
+
+        Self(words.join(" ").to_lowercase())
+    }
+
+    /// Whether any of the keywords, each a word or a phrase of several words, were said.
+    fn mentions(&self, keywords: &[String]) -> bool {
+        let padded = format!(" {} ", self.0);
+        keywords
+            .iter()
+            .any(|keyword| padded.contains(&format!(" {keyword} ")))
+    }
+}
+
+/// Distinct venue names in the schedule, in a stable order.
+pub(crate) fn venues(schedule: &Schedule) -> Vec<String> {
+    let mut venues: Vec<String> = schedule.events.iter().map(|e| e.venue.clone()).collect();
+    venues.sort();
+    venues.dedup();
+    venues
+}
+
+/// Recognizer hints for the current schedule: the fixed query phrases and venue names, which are
+/// the only things [`Intent::from_transcript`] listens for.
+pub(crate) fn hints(schedule: Option<&Schedule>, keywords: &Keywords) -> Vec<String> {
+    let mut hints = keywords.hints.clone();
+
+    if let Some(schedule) = schedule {
+        hints.extend(venues(schedule));
+    }
+
+    // Recognizers limit the number of hints, beyond which they are rejected or ignored
+    hints.truncate(MAX_HINTS);
+    hints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    #[test]
+    fn intent() {
+        let venues = ["Stage A".to_string(), "Stage B".to_string()];
+        let venue = |name: &str| Some(Intent::NextEventsAtVenue(name.to_string()));
+
+        for (language, transcript, expected) in [
+            ("en-GB", "what's on now", Some(Intent::EventsNow)),
+            ("en-GB", "what is happening", Some(Intent::EventsNow)),
+            ("en-GB", "What's on?", Some(Intent::EventsNow)),
+            (
+                "en-GB",
+                "what's starting soon",
+                Some(Intent::EventsStartingSoon),
+            ),
+            ("en-GB", "what's next", Some(Intent::NextEventsEverywhere)),
+            (
+                "en-GB",
+                "next events everywhere",
+                Some(Intent::NextEventsEverywhere),
+            ),
+            ("en-GB", "what's next at stage a", venue("Stage A")),
+            ("en-GB", "what's on at Stage B tomorrow", venue("Stage B")),
+            ("en-GB", "any talks", Some(Intent::UpcomingTalks)),
+            ("en-GB", "lectures please", Some(Intent::UpcomingTalks)),
+            ("en-GB", "workshops", Some(Intent::UpcomingWorkshops)),
+            (
+                "en-GB",
+                "is there any music",
+                Some(Intent::UpcomingPerformances),
+            ),
+            ("en-GB", "shows", Some(Intent::UpcomingPerformances)),
+            ("en-GB", "turn the lights on", None),
+            ("en-GB", "online", None),
+            ("en-GB", "stage", None),
+            ("en-GB", "Stage Alpha", None),
+            ("en-GB", "hello", None),
+            ("en-GB", "", None),
+            ("en-GB", "was läuft jetzt", None),
+            ("de-DE", "Was läuft jetzt?", Some(Intent::EventsNow)),
+            (
+                "de-DE",
+                "was beginnt gleich",
+                Some(Intent::EventsStartingSoon),
+            ),
+            (
+                "de-DE",
+                "was kommt als nächstes",
+                Some(Intent::NextEventsEverywhere),
+            ),
+            (
+                "de-DE",
+                "was kommt als nächstes auf Stage B",
+                venue("Stage B"),
+            ),
+            ("de-DE", "Vorträge", Some(Intent::UpcomingTalks)),
+            (
+                "de-DE",
+                "gibt es Workshops",
+                Some(Intent::UpcomingWorkshops),
+            ),
+            ("de-DE", "Musik", Some(Intent::UpcomingPerformances)),
+            ("de-DE", "hallo", None),
+            ("de-DE", "what's on now", None),
+        ] {
+            let language = fixtures::language(language);
+            assert_eq!(
+                Intent::from_transcript(transcript, &venues, &language.speech),
+                expected,
+                "{transcript}"
+            );
+        }
+    }
+
+    #[test]
+    fn navigation() {
+        for (language, transcript, expected) in [
+            ("en-GB", "repeat that", Some(Navigation::Repeat)),
+            ("en-GB", "Say that again", Some(Navigation::Repeat)),
+            ("en-GB", "go back", Some(Navigation::Back)),
+            ("en-GB", "previous menu", Some(Navigation::Back)),
+            ("en-GB", "what's on now", None),
+            ("en-GB", "background music", None),
+            ("en-GB", "against the clock", None),
+            ("en-GB", "zurück", None),
+            ("de-DE", "noch einmal bitte", Some(Navigation::Repeat)),
+            ("de-DE", "Nochmal", Some(Navigation::Repeat)),
+            ("de-DE", "wiederholen", Some(Navigation::Repeat)),
+            ("de-DE", "Zurück", Some(Navigation::Back)),
+            ("de-DE", "wiederholung", None),
+            ("de-DE", "einmal", None),
+            ("de-DE", "go back", None),
+        ] {
+            let language = fixtures::language(language);
+            assert_eq!(
+                Navigation::from_transcript(transcript, &language.speech),
+                expected,
+                "{transcript}"
+            );
+        }
+    }
+
+    #[test]
+    fn hints_are_in_the_callers_language() {
+        let schedule = fixtures::schedule();
+
+        let hints = super::hints(Some(&schedule), &fixtures::language("de-DE").speech);
+        assert!(hints.contains(&"was läuft jetzt".to_string()));
+        assert!(hints.ends_with(&["Stage A".to_string(), "Stage B".to_string()]));
+        assert!(!hints.contains(&"what's on now".to_string()));
+
+        let hints = Navigation::hints(&fixtures::language("de-DE").speech);
+        assert_eq!(hints, ["noch einmal", "zurück"]);
+    }
+}
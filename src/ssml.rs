@@ -0,0 +1,271 @@
+use crate::lexicon::{self, Lexicon, Pronunciation};
+use std::fmt::Write;
+
+/// Pause between the items of a list, in milliseconds.
+pub(crate) const LIST_ITEM_BREAK_MS: u32 = 600;
+
+/// Marks the start of an element when speech is encoded as text.
+const ENCODED_START: char = '\u{E000}';
+
+/// Marks the end of an element when speech is encoded as text.
+const ENCODED_END: char = '\u{E001}';
+
+#[derive(Debug, Clone)]
+enum Part {
+    Text(String),
+    Break(u32),
+    Time(String),
+    Emphasis(String),
+}
+
+/// Something to be spoken, built up from text and SSML elements, that can be rendered either as
+/// SSML or as plain text for synthesizers that do not support SSML.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Speech {
+    parts: Vec<Part>,
+}
+
+impl Speech {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn text(mut self, text: impl Into<String>) -> Self {
+        self.parts.push(Part::Text(text.into()));
+        self
+    }
+
+    /// A pause of the given number of milliseconds.
+    pub(crate) fn pause(mut self, milliseconds: u32) -> Self {
+        self.parts.push(Part::Break(milliseconds));
+        self
+    }
+
+    /// A time of day in "HH:MM" format.
+    pub(crate) fn time(mut self, time: impl Into<String>) -> Self {
+        self.parts.push(Part::Time(time.into()));
+        self
+    }
+
+    pub(crate) fn emphasis(mut self, text: impl Into<String>) -> Self {
+        self.parts.push(Part::Emphasis(text.into()));
+        self
+    }
+
+    pub(crate) fn append(mut self, other: Speech) -> Self {
+        self.parts.extend(other.parts);
+        self
+    }
+
+    /// Encodes the speech as text, so that it can pass through a text template without losing
+    /// anything that is not plain text (see [`Speech::decode`]).
+    pub(crate) fn encode(&self) -> String {
+        let mut out = String::new();
+
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => out.push_str(text),
+                Part::Break(milliseconds) => {
+                    write!(out, "{ENCODED_START}b{milliseconds}{ENCODED_END}").unwrap()
+                }
+                Part::Time(time) => write!(out, "{ENCODED_START}t{time}{ENCODED_END}").unwrap(),
+                Part::Emphasis(text) => write!(out, "{ENCODED_START}e{text}{ENCODED_END}").unwrap(),
+            }
+        }
+
+        out
+    }
+
+    /// Decodes text that may contain speech encoded with [`Speech::encode`].
+    pub(crate) fn decode(text: &str) -> Self {
+        let mut speech = Self::new();
+        let mut rest = text;
+
+        while let Some(start) = rest.find(ENCODED_START) {
+            let Some(end) = rest[start..].find(ENCODED_END).map(|end| start + end) else {
+                break;
+            };
+
+            speech = speech.text(&rest[..start]);
+
+            let element = &rest[start + ENCODED_START.len_utf8()..end];
+            let mut chars = element.chars();
+            let kind = chars.next();
+            let content = chars.as_str();
+
+            speech = match (kind, content.parse()) {
+                (Some('b'), Ok(milliseconds)) => speech.pause(milliseconds),
+                (Some('t'), _) => speech.time(content),
+                (Some('e'), _) => speech.emphasis(content),
+                _ => speech.text(content),
+            };
+
+            rest = &rest[end + ENCODED_END.len_utf8()..];
+        }
+
+        speech.text(rest)
+    }
+
+    /// Renders the speech, replacing any words found in the lexicon.
+    pub(crate) fn render(&self, ssml: bool, lexicon: &Lexicon) -> String {
+        let mut out = String::new();
+
+        if ssml {
+            out.push_str("<speak>");
+        }
+
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => render_text(&mut out, text, ssml, lexicon),
+                Part::Break(milliseconds) if ssml => {
+                    write!(out, "<break time=\"{milliseconds}ms\"/>").unwrap()
+                }
+                Part::Break(_) => out.push(' '),
+                Part::Time(time) if ssml => {
+                    out.push_str("<say-as interpret-as=\"time\" format=\"hms24\">");
+                    out.push_str(&escape(time));
+                    out.push_str("</say-as>");
+                }
+                Part::Time(time) => out.push_str(time),
+                Part::Emphasis(text) if ssml => {
+                    out.push_str("<emphasis level=\"moderate\">");
+                    render_text(&mut out, text, ssml, lexicon);
+                    out.push_str("</emphasis>");
+                }
+                Part::Emphasis(text) => render_text(&mut out, text, ssml, lexicon),
+            }
+        }
+
+        if ssml {
+            out.push_str("</speak>");
+        }
+
+        out
+    }
+}
+
+impl From<&str> for Speech {
+    fn from(text: &str) -> Self {
+        Self::new().text(text)
+    }
+}
+
+impl From<String> for Speech {
+    fn from(text: String) -> Self {
+        Self::new().text(text)
+    }
+}
+
+impl From<&String> for Speech {
+    fn from(text: &String) -> Self {
+        Self::new().text(text.as_str())
+    }
+}
+
+/// Renders text, replacing any words found in the lexicon.
+fn render_text(out: &mut String, text: &str, ssml: bool, lexicon: &Lexicon) {
+    for (token, is_word) in lexicon::tokens(text) {
+        let pronunciation = if is_word { lexicon.get(token) } else { None };
+
+        match (pronunciation, ssml) {
+            (None, true) => out.push_str(&escape(token)),
+            (None, false) => out.push_str(token),
+            (Some(Pronunciation::Substitute(alias)), true) => write!(
+                out,
+                "<sub alias=\"{}\">{}</sub>",
+                escape(alias),
+                escape(token)
+            )
+            .unwrap(),
+            (Some(Pronunciation::Substitute(alias)), false) => out.push_str(alias),
+            (Some(Pronunciation::Phoneme { ipa, .. }), true) => write!(
+                out,
+                "<phoneme alphabet=\"ipa\" ph=\"{}\">{}</phoneme>",
+                escape(ipa),
+                escape(token)
+            )
+            .unwrap(),
+            (Some(Pronunciation::Phoneme { fallback, .. }), false) => out.push_str(fallback),
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lexicon() -> Lexicon {
+        Lexicon::builtin()
+    }
+
+    #[test]
+    fn escapes_text() {
+        let speech = Speech::from(r#"Rock & roll <live> at "Stage A""#);
+
+        assert_eq!(
+            speech.render(true, &lexicon()),
+            "<speak>Rock &amp; roll &lt;live&gt; at &quot;Stage A&quot;</speak>"
+        );
+        assert_eq!(
+            speech.render(false, &lexicon()),
+            r#"Rock & roll <live> at "Stage A""#
+        );
+    }
+
+    #[test]
+    fn time() {
+        let speech = Speech::from("Starting at ").time("14:30");
+
+        assert_eq!(
+            speech.render(true, &lexicon()),
+            "<speak>Starting at <say-as interpret-as=\"time\" format=\"hms24\">14:30</say-as></speak>"
+        );
+        assert_eq!(speech.render(false, &lexicon()), "Starting at 14:30");
+    }
+
+    #[test]
+    fn elements() {
+        let speech = Speech::new()
+            .emphasis("Now")
+            .pause(LIST_ITEM_BREAK_MS)
+            .text("Welcome to EMF");
+
+        assert_eq!(
+            speech.render(true, &lexicon()),
+            "<speak><emphasis level=\"moderate\">Now</emphasis><break time=\"600ms\"/>Welcome to <sub alias=\"E M F\">EMF</sub></speak>"
+        );
+        assert_eq!(speech.render(false, &lexicon()), "Now Welcome to E M F");
+    }
+
+    #[test]
+    fn phonemes() {
+        let speech = Speech::from("Call a DECT phone");
+
+        assert_eq!(
+            speech.render(true, &lexicon()),
+            "<speak>Call a <phoneme alphabet=\"ipa\" ph=\"dɛkt\">DECT</phoneme> phone</speak>"
+        );
+        assert_eq!(speech.render(false, &lexicon()), "Call a deckt phone");
+    }
+
+    #[test]
+    fn encode_round_trip() {
+        let speech = Speech::from("At ")
+            .time("09:00")
+            .pause(300)
+            .emphasis("Stage A & B");
+        let decoded = Speech::decode(&format!("Hello. {}", speech.encode()));
+
+        assert_eq!(
+            decoded.render(true, &lexicon()),
+            "<speak>Hello. At <say-as interpret-as=\"time\" format=\"hms24\">09:00</say-as><break time=\"300ms\"/><emphasis level=\"moderate\">Stage A &amp; B</emphasis></speak>"
+        );
+    }
+}
@@ -1,42 +1,198 @@
-use crate::jambonz::{Say, SaySynthesizer, Verb};
-use chrono::{DateTime, Datelike, Duration, FixedOffset};
-
-pub(crate) fn speak(text: &str) -> Say {
-    Say {
-        text: text.to_string(),
-        synthesizer: Some(SaySynthesizer {
-            vendor: "aws".to_string(),
-            language: "en-GB".to_string(),
-            gender: None,
-            voice: "Amy".to_string(),
-        }),
+use crate::{
+    catalog::Language,
+    jambonz::{GatherRecognizer, Say, SaySynthesizer, Verb},
+    lexicon::LexiconStore,
+    ssml::Speech,
+};
+use clap::ValueEnum;
+use std::{collections::HashMap, str::FromStr, sync::Arc};
+
+/// Kinds of prompt that can be spoken in a different voice to everything else.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum)]
+pub(crate) enum PromptKind {
+    /// Greetings and farewells
+    Announcement,
+    /// Telling the caller that something went wrong
+    Error,
+}
+
+/// A voice to use for a kind of prompt, in the form `<kind>=<voice>`.
+#[derive(Debug, Clone)]
+pub(crate) struct VoiceOverride {
+    pub kind: PromptKind,
+    pub voice: String,
+}
+
+impl FromStr for VoiceOverride {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, voice) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected <kind>=<voice>, got \"{s}\""))?;
+
+        Ok(Self {
+            kind: PromptKind::from_str(kind, true)?,
+            voice: voice.to_string(),
+        })
     }
 }
 
-pub(crate) fn speak_verb(text: &str) -> Verb {
-    Verb::Say(speak(text))
+/// Turns speech into the verbs that make jambonz say it.
+#[derive(Clone)]
+pub(crate) struct Voice {
+    /// Whether the synthesizer supports SSML, otherwise plain text is sent.
+    pub ssml: bool,
+
+    /// Pronunciations applied to everything that is spoken.
+    pub lexicon: Arc<LexiconStore>,
+
+    pub synthesizer: SaySynthesizer,
+
+    /// Voices used instead of the synthesizer's voice for certain kinds of prompt.
+    pub overrides: HashMap<PromptKind, String>,
+
+    /// Speech recognizer for gathers that accept speech, completed with the caller's language and
+    /// hints for what they might say.
+    pub recognizer: GatherRecognizer,
 }
 
-pub(crate) fn format_timestamp_relative_to(
-    timestamp: DateTime<FixedOffset>,
-    now: DateTime<FixedOffset>,
-) -> String {
-    if timestamp.day() == now.day() {
-        timestamp.format("%H:%M")
-    } else {
-        timestamp.format("%A %H:%M")
+impl Voice {
+    /// Gets a voice that speaks the given language, which is this voice unless the language has a
+    /// voice of its own from the synthesizer's vendor.
+    pub(crate) fn for_language(&self, language: &Language) -> Self {
+        let Some(voice) = language.voices.get(&self.synthesizer.vendor) else {
+            return self.clone();
+        };
+
+        let mut synthesizer = SaySynthesizer {
+            language: language.code.clone(),
+            voice: voice.clone(),
+            ..self.synthesizer.clone()
+        };
+
+        let fallback = synthesizer
+            .fallback_vendor
+            .as_ref()
+            .and_then(|vendor| language.fallback_voices.get(vendor));
+        match fallback {
+            Some(fallback) => {
+                synthesizer.fallback_language = Some(language.code.clone());
+                synthesizer.fallback_voice = Some(fallback.clone());
+            }
+            None => {
+                synthesizer.fallback_vendor = None;
+                synthesizer.fallback_language = None;
+                synthesizer.fallback_voice = None;
+            }
+        }
+
+        Self {
+            synthesizer,
+            // Overrides are voices for the configured language, so would sound wrong here
+            overrides: HashMap::new(),
+            ..self.clone()
+        }
+    }
+
+    /// Checks that every language can be spoken, i.e. it is the configured language or has a voice
+    /// from the synthesizer's vendor.
+    pub(crate) fn validate(&self, languages: &[Arc<Language>]) -> anyhow::Result<()> {
+        let vendor = &self.synthesizer.vendor;
+
+        for language in languages {
+            if language.code != self.synthesizer.language && !language.voices.contains_key(vendor) {
+                anyhow::bail!(
+                    "{} ({}) has no {vendor} voice, add one to its catalog or stop offering it",
+                    language.name,
+                    language.code
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn speak(&self, speech: impl Into<Speech>) -> Say {
+        Say {
+            text: speech
+                .into()
+                .render(self.ssml, &self.lexicon.get(&self.synthesizer.language)),
+            synthesizer: Some(self.synthesizer.clone()),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn speak_verb(&self, speech: impl Into<Speech>) -> Verb {
+        Verb::Say(self.speak(speech))
+    }
+
+    /// Speaks a prompt in the voice configured for its kind, if any.
+    pub(crate) fn speak_as(&self, kind: PromptKind, speech: impl Into<Speech>) -> Say {
+        let mut say = self.speak(speech);
+
+        if let (Some(voice), Some(synthesizer)) = (self.overrides.get(&kind), &mut say.synthesizer)
+        {
+            synthesizer.voice = voice.clone();
+        }
+
+        say
+    }
+
+    pub(crate) fn speak_verb_as(&self, kind: PromptKind, speech: impl Into<Speech>) -> Verb {
+        Verb::Say(self.speak_as(kind, speech))
+    }
+
+    pub(crate) fn recognizer(&self, hints: Vec<String>, language: &Language) -> GatherRecognizer {
+        GatherRecognizer {
+            language: language.code.clone(),
+            hints,
+            ..self.recognizer.clone()
+        }
     }
-    .to_string()
 }
 
-pub(crate) fn format_duration(duration: Duration) -> String {
-    let hours = if duration.num_hours() > 0 {
-        format!("{} hours", duration.num_hours())
-    } else {
-        "".to_string()
-    };
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
 
-    let minutes = format!("{} minutes", duration.num_minutes() % 60);
+    fn voice(vendor: &str) -> Voice {
+        let mut voice = fixtures::voice();
+        voice.synthesizer = SaySynthesizer {
+            vendor: vendor.to_string(),
+            language: "en-GB".to_string(),
+            voice: "Amy".to_string(),
+            ..Default::default()
+        };
+        voice
+    }
+
+    #[test]
+    fn language_voice_is_from_the_vendor() {
+        let german = fixtures::language("de-DE");
+
+        let synthesizer = voice("aws").for_language(&german).synthesizer;
+        assert_eq!(synthesizer.language, "de-DE");
+        assert_eq!(synthesizer.voice, "Vicki");
+
+        let synthesizer = voice("google").for_language(&german).synthesizer;
+        assert_eq!(synthesizer.voice, "de-DE-Wavenet-C");
 
-    format!("{hours}{minutes}")
+        let synthesizer = voice("aws")
+            .for_language(&fixtures::language("en-GB"))
+            .synthesizer;
+        assert_eq!(synthesizer.voice, "Amy");
+    }
+
+    #[test]
+    fn languages_need_a_voice_from_the_vendor() {
+        let catalog = fixtures::catalog(&["en-GB", "de-DE"]);
+
+        assert!(voice("aws").validate(catalog.languages()).is_ok());
+        assert!(voice("elevenlabs").validate(catalog.languages()).is_err());
+
+        let catalog = fixtures::catalog(&["en-GB"]);
+        assert!(voice("elevenlabs").validate(catalog.languages()).is_ok());
+    }
 }
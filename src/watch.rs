@@ -0,0 +1,115 @@
+use anyhow::Context;
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use tokio::sync::Mutex;
+
+/// A file that is loaded again whenever it changes, as told by its modification time.
+pub(crate) struct WatchedFile {
+    path: PathBuf,
+
+    /// Modification time of the file when it was last loaded.
+    loaded: Mutex<Option<SystemTime>>,
+}
+
+impl WatchedFile {
+    pub(crate) fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            loaded: Default::default(),
+        }
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Loads the file with `load`, or gives `None` if it has not changed since it was last loaded.
+    ///
+    /// The file only counts as loaded if `load` succeeds, so a file that failed to load is tried
+    /// again next time even if it has not changed.
+    pub(crate) async fn load_if_changed<T>(
+        &self,
+        load: impl FnOnce(Vec<u8>) -> anyhow::Result<T>,
+    ) -> anyhow::Result<Option<T>> {
+        let path = &self.path;
+
+        let modified = tokio::fs::metadata(path)
+            .await
+            .and_then(|metadata| metadata.modified())
+            .with_context(|| format!("failed to stat {}", path.display()))?;
+
+        let mut loaded = self.loaded.lock().await;
+        if *loaded == Some(modified) {
+            return Ok(None);
+        }
+
+        let contents = tokio::fs::read(path)
+            .await
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let value =
+            load(contents).with_context(|| format!("failed to parse {}", path.display()))?;
+
+        *loaded = Some(modified);
+
+        Ok(Some(value))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::fixtures;
+    use std::time::Duration;
+
+    /// Sets the modification time of a file, so that tests do not depend on the resolution of the
+    /// filesystem's timestamps.
+    pub(crate) fn set_modified(path: &Path, secs: u64) {
+        std::fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+            .unwrap();
+    }
+
+    fn load(contents: Vec<u8>) -> anyhow::Result<String> {
+        Ok(String::from_utf8(contents)?)
+    }
+
+    #[tokio::test]
+    async fn loads_when_modified() {
+        let path = fixtures::temp_path("watched");
+        std::fs::write(&path, "one").unwrap();
+        set_modified(&path, 1000);
+        let file = WatchedFile::new(path.clone());
+
+        assert_eq!(file.load_if_changed(load).await.unwrap().unwrap(), "one");
+        assert!(file.load_if_changed(load).await.unwrap().is_none());
+
+        std::fs::write(&path, "two").unwrap();
+        set_modified(&path, 1000);
+        assert!(file.load_if_changed(load).await.unwrap().is_none());
+
+        set_modified(&path, 2000);
+        assert_eq!(file.load_if_changed(load).await.unwrap().unwrap(), "two");
+    }
+
+    #[tokio::test]
+    async fn failed_load_is_retried() {
+        let path = fixtures::temp_path("watched");
+        std::fs::write(&path, [0xff]).unwrap();
+        set_modified(&path, 1000);
+        let file = WatchedFile::new(path.clone());
+
+        assert!(file.load_if_changed(load).await.is_err());
+
+        std::fs::write(&path, "fixed").unwrap();
+        set_modified(&path, 1000);
+        assert_eq!(file.load_if_changed(load).await.unwrap().unwrap(), "fixed");
+
+        let file = WatchedFile::new(fixtures::temp_path("missing"));
+        assert!(file.load_if_changed(load).await.is_err());
+    }
+}
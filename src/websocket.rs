@@ -0,0 +1,234 @@
+use crate::route::Route;
+use axum::{
+    body::Body,
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    http::{header, Method, Request},
+    response::Response,
+    routing::get,
+    Router,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tower::ServiceExt;
+use tracing::{debug, error, info, warn};
+
+/// Subprotocol jambonz requests when connecting to a WebSocket application.
+const JAMBONZ_PROTOCOL: &str = "ws.jambonz.org";
+
+/// Largest response to a hook that is passed back to jambonz.
+const MAX_RESPONSE_BYTES: usize = 1024 * 1024;
+
+/// See https://www.jambonz.org/docs/ws/overview/
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+enum ClientMessage {
+    #[serde(rename = "session:new")]
+    SessionNew {
+        msgid: String,
+        call_sid: String,
+        data: Value,
+    },
+    #[serde(rename = "verb:hook")]
+    VerbHook {
+        msgid: String,
+        call_sid: String,
+        hook: String,
+        data: Value,
+    },
+    #[serde(rename = "call:status")]
+    CallStatus { call_sid: String, data: Value },
+    #[serde(rename = "jambonz:error")]
+    Error { data: Value },
+    #[serde(other)]
+    Other,
+}
+
+/// See https://www.jambonz.org/docs/ws/overview/
+#[derive(Debug, Serialize)]
+#[serde(tag = "type")]
+enum ServerMessage {
+    #[serde(rename = "ack")]
+    Ack {
+        msgid: String,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        data: Option<Value>,
+    },
+}
+
+/// Builds a router serving the jambonz WebSocket API.
+///
+/// Each message received over the WebSocket is translated into the equivalent webhook request and
+/// passed to the webhook router, so both transports share exactly the same call flows.
+pub(super) fn build_router(webhooks: Router) -> Router {
+    Router::new()
+        .route("/ws", get(upgrade))
+        .with_state(webhooks)
+}
+
+async fn upgrade(State(webhooks): State<Router>, ws: WebSocketUpgrade) -> Response {
+    ws.protocols([JAMBONZ_PROTOCOL])
+        .on_upgrade(|socket| handle_socket(socket, webhooks))
+}
+
+async fn handle_socket(mut socket: WebSocket, webhooks: Router) {
+    info!("jambonz WebSocket connected");
+
+    while let Some(message) = socket.recv().await {
+        let text = match message {
+            Ok(Message::Text(text)) => text,
+            Ok(Message::Close(_)) => break,
+            Ok(_) => continue,
+            Err(e) => {
+                warn!("WebSocket error: {e}");
+                break;
+            }
+        };
+
+        let message = match serde_json::from_str::<ClientMessage>(text.as_str()) {
+            Ok(message) => message,
+            Err(e) => {
+                warn!("Failed to parse jambonz message: {e}");
+                continue;
+            }
+        };
+
+        if let Some(reply) = handle_message(message, &webhooks).await {
+            let reply = serde_json::to_string(&reply).expect("ack should be serializable");
+            if let Err(e) = socket.send(Message::Text(reply.into())).await {
+                warn!("Failed to send to jambonz: {e}");
+                break;
+            }
+        }
+    }
+
+    info!("jambonz WebSocket disconnected");
+}
+
+async fn handle_message(message: ClientMessage, webhooks: &Router) -> Option<ServerMessage> {
+    match message {
+        ClientMessage::SessionNew {
+            msgid,
+            call_sid,
+            data,
+        } => {
+            let verbs = call_webhook(webhooks, Route::Incoming.path(), &call_sid, data).await;
+            Some(ServerMessage::Ack { msgid, data: verbs })
+        }
+        ClientMessage::VerbHook {
+            msgid,
+            call_sid,
+            hook,
+            data,
+        } => {
+            let verbs = call_webhook(webhooks, &hook, &call_sid, data).await;
+            Some(ServerMessage::Ack { msgid, data: verbs })
+        }
+        ClientMessage::CallStatus { call_sid, data } => {
+            call_webhook(webhooks, Route::CallStatus.path(), &call_sid, data).await;
+            None
+        }
+        ClientMessage::Error { data } => {
+            error!("jambonz reported an error: {data}");
+            None
+        }
+        ClientMessage::Other => {
+            debug!("Ignoring unhandled jambonz message");
+            None
+        }
+    }
+}
+
+/// Passes a hook invocation to the webhook router as if jambonz had made the HTTP request, and
+/// returns the resulting verbs (if any).
+async fn call_webhook(
+    webhooks: &Router,
+    hook: &str,
+    call_sid: &str,
+    mut data: Value,
+) -> Option<Value> {
+    // The call SID is sent alongside the payload rather than inside it
+    if let Value::Object(data) = &mut data {
+        data.entry("call_sid")
+            .or_insert_with(|| Value::String(call_sid.to_string()));
+    }
+
+    // The hook comes from jambonz, so may not be a valid URI
+    let request = match Request::builder()
+        .method(Method::POST)
+        .uri(hook)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(data.to_string()))
+    {
+        Ok(request) => request,
+        Err(e) => {
+            error!("Invalid hook {hook:?}: {e}");
+            return None;
+        }
+    };
+
+    let response = webhooks
+        .clone()
+        .oneshot(request)
+        .await
+        .expect("router should be infallible");
+
+    if !response.status().is_success() {
+        error!("Hook {hook} failed with status {}", response.status());
+        return None;
+    }
+
+    let body = match axum::body::to_bytes(response.into_body(), MAX_RESPONSE_BYTES).await {
+        Ok(body) => body,
+        Err(e) => {
+            error!("Failed to read response to hook {hook}: {e}");
+            return None;
+        }
+    };
+
+    if body.is_empty() {
+        None
+    } else {
+        serde_json::from_slice(&body)
+            .inspect_err(|e| error!("Hook {hook} did not return JSON: {e}"))
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::post;
+    use serde_json::json;
+
+    fn webhooks() -> Router {
+        Router::new().route(
+            "/call/menu",
+            post(|| async { axum::Json(json!([{"verb": "hangup"}])) }),
+        )
+    }
+
+    #[tokio::test]
+    async fn hook_is_passed_to_the_router() {
+        let verbs = call_webhook(&webhooks(), "/call/menu", "abc", json!({})).await;
+
+        assert_eq!(verbs, Some(json!([{"verb": "hangup"}])));
+    }
+
+    #[tokio::test]
+    async fn invalid_hook_is_ignored() {
+        let verbs = call_webhook(&webhooks(), "/call/menu with spaces", "abc", json!({})).await;
+
+        assert_eq!(verbs, None);
+    }
+
+    #[tokio::test]
+    async fn unknown_hook_is_ignored() {
+        let verbs = call_webhook(&webhooks(), "/call/unknown", "abc", json!({})).await;
+
+        assert_eq!(verbs, None);
+    }
+}